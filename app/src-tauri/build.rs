@@ -1,3 +1,39 @@
+use std::process::Command;
+
 fn main() {
+    emit_build_metadata();
     tauri_build::build()
 }
+
+/// Expose git sha and build date to `get_app_version` via `env!`
+/// Missing git (e.g. a tarball build) yields "unknown" instead of failing the build
+fn emit_build_metadata() {
+    let git_sha = git(&["rev-parse", "--short=12", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=YORUTSUKE_GIT_SHA={}", git_sha);
+
+    // Rebuild when HEAD moves (commit, checkout) so the sha doesn't go stale
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        println!("cargo:rerun-if-changed={}/HEAD", git_dir);
+        println!("cargo:rerun-if-changed={}/logs/HEAD", git_dir);
+    }
+
+    // Honour SOURCE_DATE_EPOCH for reproducible builds
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let build_date = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<i64>().ok())
+        .and_then(|epoch| chrono::DateTime::from_timestamp(epoch, 0))
+        .unwrap_or_else(chrono::Utc::now);
+    println!("cargo:rustc-env=YORUTSUKE_BUILD_DATE={}", build_date.format("%Y-%m-%dT%H:%M:%SZ"));
+}
+
+/// Run a git command and return its trimmed stdout, or None if git is unavailable
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let trimmed = stdout.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
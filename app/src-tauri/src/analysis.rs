@@ -0,0 +1,560 @@
+//! Image analysis on decoded originals (quality checks before upload)
+
+use std::path::Path;
+
+use image::imageops::FilterType;
+use image::GrayImage;
+
+use crate::compress::open_oriented;
+use crate::error::AppError;
+use crate::probe::sniff_format;
+use crate::task::run_blocking;
+
+/// Longest side images are scaled to before scoring, so scores don't depend on camera resolution
+const ANALYSIS_MAX_DIMENSION: u32 = 1024;
+
+/// Sharpness below which a photo is flagged as likely blurry (default)
+/// Sharp receipt photos typically score several hundred; motion blur drops well under 100
+const DEFAULT_BLUR_THRESHOLD: f64 = 100.0;
+
+/// Decode `path` upright and as grayscale, scaled down to ANALYSIS_MAX_DIMENSION
+fn load_analysis_image(path: &Path) -> Result<GrayImage, AppError> {
+    load_gray_within(path, ANALYSIS_MAX_DIMENSION)
+}
+
+/// Decode `path` upright and as grayscale, scaled down to fit `max_dimension`
+fn load_gray_within(path: &Path, max_dimension: u32) -> Result<GrayImage, AppError> {
+    if !path.exists() {
+        return Err(AppError::FileNotFound { path: path.to_string_lossy().to_string() });
+    }
+    let img = open_oriented(path, sniff_format(path)?)?;
+    let img = if img.width().max(img.height()) > max_dimension {
+        img.resize(max_dimension, max_dimension, FilterType::Triangle)
+    } else {
+        img
+    };
+    Ok(img.to_luma8())
+}
+
+/// Variance of the 4-neighbour Laplacian over interior pixels
+/// The Laplacian responds to edges, so crisp text gives a wide spread and blur a narrow one
+fn laplacian_variance(gray: &GrayImage) -> f64 {
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let at = |x: u32, y: u32| i32::from(gray.get_pixel(x, y).0[0]);
+
+    let (mut sum, mut sum_sq, mut count) = (0.0f64, 0.0f64, 0.0f64);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian = 4 * at(x, y) - at(x - 1, y) - at(x + 1, y) - at(x, y - 1) - at(x, y + 1);
+            let value = f64::from(laplacian);
+            sum += value;
+            sum_sq += value * value;
+            count += 1.0;
+        }
+    }
+    let mean = sum / count;
+    sum_sq / count - mean * mean
+}
+
+/// Result of score_image_sharpness
+#[derive(serde::Serialize)]
+pub struct SharpnessScore {
+    /// Variance of the Laplacian at 1024px (higher = sharper)
+    pub score: f64,
+    pub likely_blurry: bool,
+    /// Threshold `likely_blurry` was decided against
+    pub threshold: f64,
+}
+
+/// Score how sharp a photo is, to suggest a retake before it is uploaded
+/// The score is the variance of the Laplacian of the grayscale image scaled to 1024px on
+/// its longest side: higher means sharper. `likely_blurry` is `score < threshold`
+/// (default 100); blank or very low-contrast images also score low
+#[tauri::command]
+pub async fn score_image_sharpness(input_path: String, threshold: Option<f64>) -> Result<SharpnessScore, AppError> {
+    let threshold = threshold.unwrap_or(DEFAULT_BLUR_THRESHOLD);
+    if !threshold.is_finite() || threshold < 0.0 {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid blur threshold: {} (expected >= 0)", threshold),
+        });
+    }
+    run_blocking(move || {
+        let score = laplacian_variance(&load_analysis_image(Path::new(&input_path))?);
+        Ok(SharpnessScore { score, likely_blurry: score < threshold, threshold })
+    })
+    .await
+}
+
+/// Script detection needs more resolution than sharpness: kanji strokes merge below ~20px
+const SCRIPT_MAX_DIMENSION: u32 = 1600;
+
+/// Fewer classified glyphs than this and the hint is "unknown"
+const MIN_SCRIPT_GLYPHS: usize = 30;
+
+/// Share of CJK-like glyphs at or above which the hint is "ja" (Japanese receipts are
+/// still full of digits and Latin product codes), and at or below which it is "en"
+const JA_MIN_RATIO: f64 = 0.35;
+const EN_MAX_RATIO: f64 = 0.08;
+
+/// Otsu's threshold: the gray level that best separates ink from paper
+pub fn otsu_threshold(gray: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    let total = gray.pixels().len() as f64;
+    let sum_all: f64 = histogram.iter().enumerate().map(|(level, &n)| level as f64 * n as f64).sum();
+
+    let (mut best, mut best_variance) = (0u8, 0.0f64);
+    let (mut weight_bg, mut sum_bg) = (0.0f64, 0.0f64);
+    for (level, &n) in histogram.iter().enumerate() {
+        weight_bg += n as f64;
+        if weight_bg == 0.0 || weight_bg == total {
+            continue;
+        }
+        sum_bg += level as f64 * n as f64;
+        let weight_fg = total - weight_bg;
+        let mean_bg = sum_bg / weight_bg;
+        let mean_fg = (sum_all - sum_bg) / weight_fg;
+        let variance = weight_bg * weight_fg * (mean_bg - mean_fg).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = level as u8;
+        }
+    }
+    best
+}
+
+/// Number of ink runs along one column of a glyph box
+fn vertical_runs(ink: &[bool], width: usize, x: usize, rows: std::ops::Range<usize>) -> usize {
+    let mut runs = 0;
+    let mut inside = false;
+    for y in rows {
+        let here = ink[y * width + x];
+        if here && !inside {
+            runs += 1;
+        }
+        inside = here;
+    }
+    runs
+}
+
+/// Glyph counts from a text-line scan: (CJK-like, Latin-like)
+/// Lines are bands of inked rows; glyphs are runs of inked columns within a line.
+/// A CJK glyph is roughly square, fills the line height and is crossed by several
+/// horizontal strokes; Latin letters are narrow or x-height only, and touching letters
+/// form wide word blobs
+fn count_script_glyphs(gray: &GrayImage) -> (usize, usize) {
+    let (width, height) = (gray.width() as usize, gray.height() as usize);
+    let threshold = otsu_threshold(gray);
+    let ink: Vec<bool> = gray.pixels().map(|p| p.0[0] < threshold).collect();
+
+    let min_row_ink = (width / 100).max(2);
+    let row_has_text: Vec<bool> =
+        (0..height).map(|y| ink[y * width..(y + 1) * width].iter().filter(|&&i| i).count() >= min_row_ink).collect();
+
+    let (mut cjk, mut latin) = (0, 0);
+    let mut y = 0;
+    while y < height {
+        if !row_has_text[y] {
+            y += 1;
+            continue;
+        }
+        let top = y;
+        while y < height && row_has_text[y] {
+            y += 1;
+        }
+        let line_height = y - top;
+        if line_height < 8 || line_height > height / 8 {
+            continue;
+        }
+
+        let column_has_ink = |x: usize| (top..y).any(|row| ink[row * width + x]);
+        let mut x = 0;
+        while x < width {
+            if !column_has_ink(x) {
+                x += 1;
+                continue;
+            }
+            let left = x;
+            while x < width && column_has_ink(x) {
+                x += 1;
+            }
+            let glyph_width = x - left;
+            if glyph_width < 2 {
+                continue;
+            }
+            // Trim the glyph box to its own inked rows
+            let inked_row = |row: usize| ink[row * width + left..row * width + x].iter().any(|&i| i);
+            let Some(glyph_top) = (top..y).find(|&row| inked_row(row)) else { continue };
+            let glyph_bottom = (top..y).rev().find(|&row| inked_row(row)).unwrap_or(glyph_top) + 1;
+            let glyph_height = glyph_bottom - glyph_top;
+
+            let aspect = glyph_width as f64 / glyph_height as f64;
+            let fills_line = glyph_height as f64 >= 0.8 * line_height as f64;
+            let strokes = (left..x)
+                .map(|column| vertical_runs(&ink, width, column, glyph_top..glyph_bottom))
+                .sum::<usize>() as f64
+                / glyph_width as f64;
+
+            if fills_line && (0.75..=1.35).contains(&aspect) && strokes >= 2.5 {
+                cjk += 1;
+            } else if !fills_line || !(0.6..=1.6).contains(&aspect) {
+                latin += 1;
+            }
+        }
+    }
+    (cjk, latin)
+}
+
+/// Result of detect_script_hint
+#[derive(serde::Serialize)]
+pub struct ScriptHint {
+    /// "ja", "en" or "unknown"
+    pub hint: String,
+    /// 0-1; always 0 for "unknown"
+    pub confidence: f64,
+    /// Glyph-sized shapes the decision was based on
+    pub glyphs_examined: usize,
+}
+
+/// Guess whether a receipt is Japanese or English, to hint the OCR language
+/// A layout heuristic, not a classifier: text lines are segmented into glyph-sized
+/// shapes and the share of square, stroke-dense (kanji/kana-like) shapes decides.
+/// Best effort only: returns "unknown" when too few glyphs are found or the share is
+/// ambiguous; the same image always yields the same hint
+#[tauri::command]
+pub async fn detect_script_hint(input_path: String) -> Result<ScriptHint, AppError> {
+    run_blocking(move || {
+        let gray = load_gray_within(Path::new(&input_path), SCRIPT_MAX_DIMENSION)?;
+        let (cjk, latin) = count_script_glyphs(&gray);
+        let examined = cjk + latin;
+        if examined < MIN_SCRIPT_GLYPHS {
+            return Ok(ScriptHint { hint: "unknown".to_string(), confidence: 0.0, glyphs_examined: examined });
+        }
+
+        let ratio = cjk as f64 / examined as f64;
+        // Fewer glyphs means a noisier ratio; full weight from 100 glyphs
+        let sample_weight = (examined as f64 / 100.0).min(1.0);
+        let (hint, confidence) = if ratio >= JA_MIN_RATIO {
+            ("ja", 0.5 + 0.5 * (ratio - JA_MIN_RATIO) / (1.0 - JA_MIN_RATIO))
+        } else if ratio <= EN_MAX_RATIO {
+            ("en", 0.5 + 0.5 * (EN_MAX_RATIO - ratio) / EN_MAX_RATIO)
+        } else {
+            ("unknown", 0.0)
+        };
+        Ok(ScriptHint {
+            hint: hint.to_string(),
+            confidence: confidence * sample_weight,
+            glyphs_examined: examined,
+        })
+    })
+    .await
+}
+
+/// Long/short side ratio at which the aspect signal starts to rise, and where it is full;
+/// a till receipt is usually 2-4x as long as it is wide
+const RECEIPT_ASPECT_RANGE: (f64, f64) = (1.2, 2.0);
+
+/// Aspect score of a square-ish image: the photo may show a receipt lying on a table,
+/// so the shape alone never counts against it much
+const RECEIPT_ASPECT_FLOOR: f64 = 0.3;
+
+/// Gray level from which a pixel counts as paper-white
+const PAPER_WHITE_LEVEL: u8 = 170;
+
+/// Share of paper-white pixels at which the background signal starts, and where it is full
+const RECEIPT_WHITE_RANGE: (f64, f64) = (0.15, 0.55);
+
+/// Horizontal neighbour difference that counts as a glyph edge
+const TEXT_EDGE_STEP: i16 = 48;
+
+/// Glyph edges per pixel of width for a row to count as part of a text line
+const TEXT_ROW_EDGE_DENSITY: f64 = 0.02;
+
+/// Separate text lines (bands of edge-dense rows) needed for a full text signal
+const RECEIPT_FULL_TEXT_LINES: usize = 12;
+
+/// Weights of the aspect, background and text signals in the combined score
+const RECEIPT_WEIGHTS: (f64, f64, f64) = (0.2, 0.35, 0.45);
+
+/// Combined score at or above which an image is treated as a receipt; deliberately low
+/// so that only clearly non-receipt photos are held back
+const RECEIPT_MIN_SCORE: f64 = 0.3;
+
+/// 0 at `range.0`, 1 at `range.1`, linear in between
+fn ramp(value: f64, range: (f64, f64)) -> f64 {
+    ((value - range.0) / (range.1 - range.0)).clamp(0.0, 1.0)
+}
+
+/// Number of text-line-like bands: runs of consecutive rows dense with glyph edges
+/// (sharp left-right brightness changes), at least 2 rows tall
+fn count_text_lines(gray: &GrayImage) -> usize {
+    let (width, height) = gray.dimensions();
+    if width < 2 {
+        return 0;
+    }
+    let min_edges = (f64::from(width) * TEXT_ROW_EDGE_DENSITY).max(1.0) as usize;
+    let texty_rows = (0..height).map(|y| {
+        (0..width - 1)
+            .filter(|&x| {
+                let step = i16::from(gray.get_pixel(x + 1, y).0[0]) - i16::from(gray.get_pixel(x, y).0[0]);
+                step.abs() >= TEXT_EDGE_STEP
+            })
+            .count()
+            >= min_edges
+    });
+
+    let (mut lines, mut run) = (0, 0);
+    for texty in texty_rows.chain(std::iter::once(false)) {
+        if texty {
+            run += 1;
+        } else {
+            if run >= 2 {
+                lines += 1;
+            }
+            run = 0;
+        }
+    }
+    lines
+}
+
+/// Result of is_likely_receipt
+#[derive(serde::Serialize)]
+pub struct ReceiptLikelihood {
+    pub is_receipt: bool,
+    /// Combined receipt-likeness score, 0-1 (`is_receipt` is score >= 0.3)
+    pub confidence: f64,
+    /// One line per signal, e.g. "aspect ratio 2.7 (receipt-shaped)"
+    pub reasons: Vec<String>,
+}
+
+/// Best-effort gate against uploading photos that clearly aren't receipts
+/// A heuristic, not a classifier, and biased toward letting images through. Signals, on
+/// the image scaled to 1024px:
+/// - shape: long/short side ratio (receipts are long and narrow); weak, since a receipt
+///   photographed on a table is often 4:3
+/// - background: share of near-white (paper) pixels
+/// - text: number of horizontal bands dense with sharp glyph edges, i.e. printed lines
+///
+/// Each signal is scored 0-1 and combined with fixed weights; the thresholds above are
+/// the knobs to tune against real uploads
+#[tauri::command]
+pub async fn is_likely_receipt(input_path: String) -> Result<ReceiptLikelihood, AppError> {
+    run_blocking(move || {
+        let gray = load_analysis_image(Path::new(&input_path))?;
+        let (width, height) = gray.dimensions();
+        if width == 0 || height == 0 {
+            return Err(AppError::DecodeFailed { reason: "Image has no pixels".to_string() });
+        }
+
+        let aspect = f64::from(width.max(height)) / f64::from(width.min(height));
+        let aspect_score = RECEIPT_ASPECT_FLOOR + (1.0 - RECEIPT_ASPECT_FLOOR) * ramp(aspect, RECEIPT_ASPECT_RANGE);
+        let white = gray.pixels().filter(|p| p.0[0] >= PAPER_WHITE_LEVEL).count() as f64
+            / (f64::from(width) * f64::from(height));
+        let white_score = ramp(white, RECEIPT_WHITE_RANGE);
+        let lines = count_text_lines(&gray);
+        let text_score = (lines as f64 / RECEIPT_FULL_TEXT_LINES as f64).min(1.0);
+
+        let (aspect_weight, white_weight, text_weight) = RECEIPT_WEIGHTS;
+        let score = aspect_weight * aspect_score + white_weight * white_score + text_weight * text_score;
+        let describe = |signal_score: f64, good: &str, bad: &str| {
+            if signal_score >= 0.5 { good.to_string() } else { bad.to_string() }
+        };
+        let reasons = vec![
+            format!("aspect ratio {:.1} ({})", aspect, describe(aspect_score, "receipt-shaped", "not elongated")),
+            format!(
+                "{:.0}% near-white pixels ({})",
+                white * 100.0,
+                describe(white_score, "paper-like background", "little paper background")
+            ),
+            format!("{} text-like lines ({})", lines, describe(text_score, "printed text", "little printed text")),
+        ];
+        let likelihood = ReceiptLikelihood { is_receipt: score >= RECEIPT_MIN_SCORE, confidence: score, reasons };
+        tracing::debug!(score, aspect, white, lines, "receipt_likelihood");
+        Ok(likelihood)
+    })
+    .await
+}
+
+/// Gray-level difference above which a pixel counts as differing in image_diff
+/// (re-encoding a JPEG shifts most pixels by a few levels without visible change)
+const DIFF_TOLERANCE: u8 = 8;
+
+/// Result of image_diff
+#[derive(serde::Serialize)]
+pub struct ImageDiff {
+    /// Mean absolute gray-level difference, 0-255
+    pub mean_abs_diff: f64,
+    pub max_diff: u8,
+    /// Share of pixels differing by more than 8 gray levels, 0-1
+    pub differing_pixel_ratio: f64,
+    /// Dimensions compared (image a's)
+    pub width: u32,
+    pub height: u32,
+    /// True when b had other dimensions and was resized to a's before comparing
+    pub resized: bool,
+}
+
+/// Decode `path` upright as full-size grayscale
+fn load_gray_full(path: &Path) -> Result<GrayImage, AppError> {
+    if !path.exists() {
+        return Err(AppError::FileNotFound { path: path.to_string_lossy().to_string() });
+    }
+    Ok(open_oriented(path, sniff_format(path)?)?.to_luma8())
+}
+
+/// Compare two images pixel by pixel as grayscale, e.g. a source and its recompressed
+/// copy in QA, or before/after an edit. When the dimensions differ, b is resized to a's
+/// (reported as `resized`); DECODE_FAILED / UNSUPPORTED_FORMAT when either can't be read
+#[tauri::command]
+pub async fn image_diff(path_a: String, path_b: String) -> Result<ImageDiff, AppError> {
+    run_blocking(move || {
+        let a = load_gray_full(Path::new(&path_a))?;
+        let b = load_gray_full(Path::new(&path_b))?;
+        let (width, height) = a.dimensions();
+        let resized = b.dimensions() != a.dimensions();
+        let b = if resized { image::imageops::resize(&b, width, height, FilterType::Triangle) } else { b };
+
+        let (mut sum, mut max_diff, mut differing) = (0u64, 0u8, 0u64);
+        for (pa, pb) in a.pixels().zip(b.pixels()) {
+            let diff = pa.0[0].abs_diff(pb.0[0]);
+            sum += u64::from(diff);
+            max_diff = max_diff.max(diff);
+            if diff > DIFF_TOLERANCE {
+                differing += 1;
+            }
+        }
+        let count = (u64::from(width) * u64::from(height)).max(1) as f64;
+        Ok(ImageDiff {
+            mean_abs_diff: sum as f64 / count,
+            max_diff,
+            differing_pixel_ratio: differing as f64 / count,
+            width,
+            height,
+            resized,
+        })
+    })
+    .await
+}
+
+/// Longest side detect_rotation works at; line structure survives well below this
+const ROTATION_MAX_DIMENSION: u32 = 512;
+
+/// Share of ink pixels outside which the image is not treated as printed text
+/// (blank pages, or photos where Otsu splits table from paper instead of ink from paper)
+const ROTATION_INK_RANGE: (f64, f64) = (0.005, 0.4);
+
+/// Column-profile variance this many times the row-profile variance means text lines
+/// run vertically, i.e. the receipt lies on its side
+const SIDEWAYS_VARIANCE_RATIO: f64 = 2.0;
+
+/// Share of the height at each end compared for ink density (header vs footer)
+const ROTATION_EDGE_SHARE: f64 = 0.25;
+
+/// Top-bottom ink lean (-1 all bottom, 1 all top) from which the direction is trusted,
+/// and where confidence is full
+const ROTATION_LEAN_RANGE: (f64, f64) = (0.25, 0.6);
+
+/// Variance of the ink share of rows (or columns with `by_column`)
+/// Horizontal text lines alternate inked and blank rows, so the row profile varies far
+/// more than the column profile on an upright receipt
+fn ink_profile_variance(ink: &GrayImage, by_column: bool) -> f64 {
+    let (width, height) = ink.dimensions();
+    let (lines, span) = if by_column { (width, height) } else { (height, width) };
+    let shares: Vec<f64> = (0..lines)
+        .map(|i| {
+            let count = (0..span)
+                .filter(|&j| {
+                    let (x, y) = if by_column { (i, j) } else { (j, i) };
+                    ink.get_pixel(x, y).0[0] > 0
+                })
+                .count();
+            count as f64 / f64::from(span)
+        })
+        .collect();
+    let mean = shares.iter().sum::<f64>() / shares.len() as f64;
+    shares.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / shares.len() as f64
+}
+
+/// Top-bottom ink lean of `ink`: (top - bottom) / (top + bottom) over the end bands
+fn ink_lean(ink: &GrayImage) -> f64 {
+    let (width, height) = ink.dimensions();
+    let band = ((f64::from(height) * ROTATION_EDGE_SHARE) as u32).max(1);
+    let count_rows = |rows: std::ops::Range<u32>| {
+        rows.flat_map(|y| (0..width).map(move |x| (x, y))).filter(|&(x, y)| ink.get_pixel(x, y).0[0] > 0).count()
+            as f64
+    };
+    let top = count_rows(0..band);
+    let bottom = count_rows(height.saturating_sub(band)..height);
+    if top + bottom == 0.0 {
+        0.0
+    } else {
+        (top - bottom) / (top + bottom)
+    }
+}
+
+/// Result of detect_rotation
+#[derive(serde::Serialize)]
+pub struct RotationSuggestion {
+    /// Clockwise degrees to turn the image upright (as rotate_stored_image takes them):
+    /// 0, 90, 180 or 270
+    pub suggested_rotation: u32,
+    /// 0-1; at least 0.5 for a confident call, below it when 0 is only the unsure default
+    pub confidence: f64,
+}
+
+/// Guess whether a receipt without EXIF orientation (usually a scan) is on its side or
+/// upside down, so the UI can offer to auto-rotate
+/// On an ink mask of the image scaled to 512px: text lines running vertically mean the
+/// receipt is sideways; then the end with more ink (headers and logos) is taken as the
+/// top. Conservative: anything ambiguous returns 0 with low confidence
+#[tauri::command]
+pub async fn detect_rotation(input_path: String) -> Result<RotationSuggestion, AppError> {
+    run_blocking(move || {
+        let gray = load_gray_within(Path::new(&input_path), ROTATION_MAX_DIMENSION)?;
+        let (width, height) = gray.dimensions();
+        if width == 0 || height == 0 {
+            return Err(AppError::DecodeFailed { reason: "Image has no pixels".to_string() });
+        }
+        let unsure = |confidence: f64| Ok(RotationSuggestion { suggested_rotation: 0, confidence });
+
+        let threshold = otsu_threshold(&gray);
+        let ink = GrayImage::from_fn(width, height, |x, y| {
+            image::Luma([if gray.get_pixel(x, y).0[0] <= threshold { 255 } else { 0 }])
+        });
+        let ink_share = ink.pixels().filter(|p| p.0[0] > 0).count() as f64 / (f64::from(width) * f64::from(height));
+        if !(ROTATION_INK_RANGE.0..=ROTATION_INK_RANGE.1).contains(&ink_share) {
+            return unsure(0.0);
+        }
+
+        let row_variance = ink_profile_variance(&ink, false);
+        let column_variance = ink_profile_variance(&ink, true);
+        let sideways = column_variance >= row_variance * SIDEWAYS_VARIANCE_RATIO;
+        // Turned a quarter clockwise, sideways text lines become horizontal
+        let upright_candidate = if sideways { image::imageops::rotate90(&ink) } else { ink };
+        let lean = ink_lean(&upright_candidate);
+        tracing::debug!(ink_share, row_variance, column_variance, sideways, lean, "rotation_estimate");
+
+        if lean.abs() < ROTATION_LEAN_RANGE.0 {
+            // Direction unknown; for a sideways receipt 0 is known to be wrong, so no confidence
+            return unsure(if sideways { 0.0 } else { 0.5 * lean.abs() / ROTATION_LEAN_RANGE.0 });
+        }
+        let mut certainty = ramp(lean.abs(), ROTATION_LEAN_RANGE);
+        let suggested_rotation = match (sideways, lean > 0.0) {
+            (false, true) => 0,
+            (false, false) => 180,
+            (true, top_heavy) => {
+                // Less sure of a sideways call when the profiles barely differ
+                let ratio = column_variance / row_variance.max(f64::EPSILON);
+                certainty *= ramp(ratio, (SIDEWAYS_VARIANCE_RATIO, 2.0 * SIDEWAYS_VARIANCE_RATIO));
+                if top_heavy { 90 } else { 270 }
+            }
+        };
+        Ok(RotationSuggestion { suggested_rotation, confidence: 0.5 + 0.5 * certainty })
+    })
+    .await
+}
@@ -0,0 +1,106 @@
+//! Build and version metadata (shown in the footer and attached to bug reports)
+//! plus a runtime self-test for the Help menu
+
+use tauri::{AppHandle, Manager};
+
+use crate::config::config_path;
+use crate::database::database_path;
+use crate::error::AppError;
+use crate::logging::{get_logs_dir, logs_dir};
+use crate::storage::{ensure_writable_dir, get_data_dir, images_dir, storage_root};
+
+/// Version details returned by get_app_version
+#[derive(serde::Serialize)]
+pub struct AppVersion {
+    pub version: String,
+    pub tauri_version: String,
+    pub git_sha: String,
+    pub build_date: String,
+}
+
+/// Get the app version as actually built (crate version, Tauri version, git sha, build date)
+/// `git_sha` and `build_date` are set by build.rs; `git_sha` is "unknown" outside a git checkout
+#[tauri::command]
+pub fn get_app_version() -> AppVersion {
+    AppVersion {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        git_sha: env!("YORUTSUKE_GIT_SHA").to_string(),
+        build_date: env!("YORUTSUKE_BUILD_DATE").to_string(),
+    }
+}
+
+/// Result of get_runtime_diagnostics; every field is true when healthy
+#[derive(serde::Serialize)]
+pub struct RuntimeDiagnostics {
+    /// SQL plugin state is set up and the database file, once created, is not empty
+    pub sql_plugin: bool,
+    /// Size of the database file (None before the frontend first opens it)
+    pub database_size: Option<u64>,
+    pub http_plugin: bool,
+    pub fs_plugin: bool,
+    pub data_dir_writable: bool,
+    pub logs_dir_writable: bool,
+}
+
+/// Self-test of the subsystems the app can't run without, for "Help > Diagnostics"
+/// Cheap checks only: plugin state is present (plugins set up during startup), the
+/// database file isn't the 0-byte file a failed SQL init leaves behind, and the
+/// images and logs directories accept a probe write.
+/// tauri-plugin-http manages no public state, so `http_plugin` only confirms startup
+/// completed (a failing plugin setup aborts the app before any command runs)
+#[tauri::command]
+pub fn get_runtime_diagnostics(app: AppHandle) -> RuntimeDiagnostics {
+    let database_size = database_path(&app)
+        .ok()
+        .and_then(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len());
+    let sql_registered = app.try_state::<tauri_plugin_sql::DbInstances>().is_some();
+
+    let data_dir_writable = get_data_dir().and_then(|dir| ensure_writable_dir(&dir)).is_ok();
+    let logs_dir_writable = ensure_writable_dir(&get_logs_dir()).is_ok();
+
+    let diagnostics = RuntimeDiagnostics {
+        sql_plugin: sql_registered && database_size != Some(0),
+        database_size,
+        http_plugin: true,
+        fs_plugin: app.try_state::<tauri_plugin_fs::Fs<tauri::Wry>>().is_some(),
+        data_dir_writable,
+        logs_dir_writable,
+    };
+    tracing::info!(
+        sql_plugin = diagnostics.sql_plugin,
+        database_size = ?diagnostics.database_size,
+        fs_plugin = diagnostics.fs_plugin,
+        data_dir_writable,
+        logs_dir_writable,
+        "runtime_diagnostics"
+    );
+    diagnostics
+}
+
+/// Resolved locations returned by get_app_paths (absolute paths)
+#[derive(serde::Serialize)]
+pub struct AppPaths {
+    /// Storage root holding `images/` (custom or platform default)
+    pub data_dir: String,
+    pub images_dir: String,
+    pub logs_dir: String,
+    pub config_path: String,
+    pub database_path: String,
+}
+
+/// Where everything lives on this machine, resolved exactly as the rest of the app does
+/// (for support and for the frontend, instead of rebuilding paths itself)
+/// Reporting only: nothing is created, so paths may not exist yet on a fresh install
+#[tauri::command]
+pub fn get_app_paths(app: AppHandle) -> Result<AppPaths, AppError> {
+    let path_string = |path: std::path::PathBuf| path.to_string_lossy().to_string();
+    Ok(AppPaths {
+        data_dir: path_string(storage_root()?),
+        images_dir: path_string(images_dir()?),
+        logs_dir: path_string(logs_dir()),
+        config_path: path_string(config_path()),
+        database_path: path_string(database_path(&app)?),
+    })
+}
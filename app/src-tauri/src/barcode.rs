@@ -0,0 +1,77 @@
+//! QR code detection on receipt photos
+//! Invoice-compliant Japanese receipts often carry a QR code with the total and store
+//! details; decoding it locally lets the frontend skip OCR for those fields
+
+use std::path::Path;
+
+use crate::compress::open_oriented;
+use crate::error::AppError;
+use crate::probe::sniff_format;
+use crate::task::run_blocking;
+
+/// Axis-aligned box around a detected code, in upright source pixels
+#[derive(serde::Serialize)]
+pub struct CodeBounds {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A decoded code found on the receipt
+#[derive(serde::Serialize)]
+pub struct ReceiptCode {
+    /// Symbology; currently always "qr"
+    pub kind: String,
+    pub data: String,
+    pub bounds: CodeBounds,
+}
+
+/// Smallest rectangle containing the four corners reported by the detector
+fn bounds_of(corners: &[rqrr::Point; 4]) -> CodeBounds {
+    let clamp = |v: i32| v.max(0) as u32;
+    let min_x = corners.iter().map(|p| p.x).min().unwrap_or(0);
+    let max_x = corners.iter().map(|p| p.x).max().unwrap_or(0);
+    let min_y = corners.iter().map(|p| p.y).min().unwrap_or(0);
+    let max_y = corners.iter().map(|p| p.y).max().unwrap_or(0);
+    CodeBounds {
+        x: clamp(min_x),
+        y: clamp(min_y),
+        width: clamp(max_x) - clamp(min_x),
+        height: clamp(max_y) - clamp(min_y),
+    }
+}
+
+/// Find and decode QR codes on the original (uncompressed) image
+/// Full resolution matters here: dense codes often don't survive the 1536px resize
+/// Returns an empty list when no code is present; detected codes that fail to decode
+/// (blurred, partly cut off) are skipped
+#[tauri::command]
+pub async fn decode_receipt_codes(input_path: String) -> Result<Vec<ReceiptCode>, AppError> {
+    run_blocking(move || {
+        let path = Path::new(&input_path);
+        if !path.exists() {
+            return Err(AppError::FileNotFound { path: input_path.clone() });
+        }
+        let luma = open_oriented(path, sniff_format(path)?)?.to_luma8();
+        let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+            luma.width() as usize,
+            luma.height() as usize,
+            |x, y| luma.get_pixel(x as u32, y as u32).0[0],
+        );
+
+        let mut codes = Vec::new();
+        for grid in prepared.detect_grids() {
+            match grid.decode() {
+                Ok((_, data)) => codes.push(ReceiptCode {
+                    kind: "qr".to_string(),
+                    data,
+                    bounds: bounds_of(&grid.bounds),
+                }),
+                Err(e) => tracing::debug!(error = %e, "qr_decode_failed"),
+            }
+        }
+        Ok(codes)
+    })
+    .await
+}
@@ -0,0 +1,2172 @@
+//! Image compression pipeline (decode → orient → resize → grayscale → encode)
+
+use std::borrow::Cow;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder};
+use image::metadata::Orientation;
+use image::{DynamicImage, GenericImageView, ImageDecoder, ImageEncoder};
+use rayon::prelude::*;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::crypto::{self, StorageKey, ENCRYPTED_EXTENSION};
+use crate::error::AppError;
+use crate::hash::{hash_file, HashAlgorithm, HashingWriter};
+use crate::probe::{sniff_format, SourceFormat};
+use crate::storage::{
+    commit_staged, ensure_free_space, get_data_dir, parse_stored_image_name, resolve_output_dir, validate_image_id,
+    write_atomic, write_staged,
+    ORIGINAL_SUFFIX,
+};
+use crate::task::run_blocking;
+use crate::webp;
+
+/// Compression result returned to frontend
+#[derive(serde::Serialize)]
+pub struct CompressResult {
+    pub success: bool,
+    pub id: String,
+    pub original_path: String,
+    pub output_path: String,
+    pub original_size: u64,
+    pub compressed_size: u64,
+    pub width: u32,
+    pub height: u32,
+    pub md5: String,
+    /// Encoder quality (None when the source was stored unchanged)
+    pub quality_used: Option<u8>,
+    pub max_dimension_used: u32,
+    /// False when the image was stored in color (`grayscale: false`)
+    pub grayscale: bool,
+    /// Format actually written ("jpeg", "webp" or "png"); the winner when `format: "auto"`
+    pub chosen_format: String,
+    /// Crop applied by `autoCrop`, in upright source pixels (None when not cropped)
+    pub crop_applied: Option<CropRect>,
+    /// True when the source was stored unchanged instead of re-encoded (small JPEGs)
+    pub was_passthrough: bool,
+    /// Wall-clock time per pipeline phase
+    pub timings_ms: PhaseTimings,
+    /// Gray levels the image was posterized to (None when not posterized)
+    pub gray_levels_used: Option<u16>,
+    /// Untouched copy of the source, when `keepOriginal` was set
+    pub original_stored_path: Option<String>,
+    /// True when `enhance` found the image low-contrast and stretched it
+    pub enhanced: bool,
+}
+
+/// Milliseconds spent in each compress phase (fractional; 0 for skipped phases)
+/// `total` also covers sniffing, writing and verification, so it exceeds the sum
+#[derive(Clone, Copy, Debug, Default, serde::Serialize)]
+pub struct PhaseTimings {
+    /// Decode and EXIF orientation (header + file read for passthrough)
+    pub decode: f64,
+    /// Auto-crop and resize
+    pub resize: f64,
+    pub grayscale: f64,
+    /// Encoding, plus writing the file when it is streamed straight to disk
+    pub encode: f64,
+    /// Kept for compatibility; 0 now that the MD5 is taken while encoding
+    pub hash: f64,
+    pub total: f64,
+}
+
+fn elapsed_ms(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Rectangle in image pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Default JPEG quality (balanced for OCR and file size)
+const DEFAULT_JPEG_QUALITY: u8 = 75;
+
+/// Default longest-side limit (tuned for Nova Lite OCR)
+pub const DEFAULT_MAX_DIMENSION: u32 = 1536;
+
+/// Allowed range for `max_dimension`; values outside are clamped
+const MAX_DIMENSION_RANGE: (u32, u32) = (256, 4096);
+
+/// JPEG size below which `format: "auto"` skips trying WebP (default)
+const DEFAULT_AUTO_WEBP_THRESHOLD: u64 = 50 * 1024;
+
+/// Luma below which a pixel counts as content rather than paper/scanner background
+const CROP_INK_THRESHOLD: u8 = 180;
+
+/// A row or column needs this fraction of ink pixels to count (ignores dust specks)
+const CROP_MIN_INK_FRACTION: f64 = 0.002;
+
+/// Content boxes smaller than this fraction of the image are distrusted (faint receipts)
+const CROP_MIN_AREA_FRACTION: f64 = 0.2;
+
+/// Output encoding for compressed images
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Jpeg,
+    WebP,
+    Png,
+}
+
+/// The `format` argument: a fixed encoding, or "auto" to keep the smallest candidate
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FormatSelection {
+    Fixed(OutputFormat),
+    Auto,
+}
+
+impl FormatSelection {
+    /// Parse the `format` argument from the frontend (defaults to JPEG)
+    fn parse(format: Option<&str>) -> Result<Self, AppError> {
+        match format.unwrap_or("jpeg") {
+            "jpeg" => Ok(FormatSelection::Fixed(OutputFormat::Jpeg)),
+            "webp" => Ok(FormatSelection::Fixed(OutputFormat::WebP)),
+            "png" => Ok(FormatSelection::Fixed(OutputFormat::Png)),
+            "auto" => Ok(FormatSelection::Auto),
+            other => Err(AppError::UnsupportedFormat { format: other.to_string() }),
+        }
+    }
+}
+
+impl OutputFormat {
+    fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpeg",
+            OutputFormat::WebP => "webp",
+            OutputFormat::Png => "png",
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::WebP => "image/webp",
+            OutputFormat::Png => "image/png",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::WebP => "webp",
+            OutputFormat::Png => "png",
+        }
+    }
+}
+
+/// Decode an image and apply its EXIF orientation so the pixels are upright
+/// `format` comes from content sniffing, so the file extension is not trusted
+/// Missing or corrupt EXIF data is treated as "no transform" rather than an error
+pub fn open_oriented(path: &Path, format: SourceFormat) -> Result<DynamicImage, AppError> {
+    let mut decoder = format
+        .reader(path)?
+        .into_decoder()
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to open image: {}", e) })?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+
+    let mut img = DynamicImage::from_decoder(decoder)
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to open image: {}", e) })?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+/// Optional compression settings, passed from the frontend as `options: { ... }`
+/// Every field may be omitted to get the defaults
+#[derive(Default, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressArgs {
+    quality: Option<u8>,
+    format: Option<String>,
+    max_dimension: Option<u32>,
+    grayscale: Option<bool>,
+    verify_output: Option<bool>,
+    /// `format: "auto"` only: skip WebP when the JPEG is already smaller than this (bytes)
+    auto_webp_threshold: Option<u64>,
+    /// `format: "auto"` only: also try PNG (off by default, it rarely wins on photos)
+    auto_include_png: Option<bool>,
+    output_dir: Option<String>,
+    durable: Option<bool>,
+    auto_crop: Option<bool>,
+    force_reencode: Option<bool>,
+    /// Required when encrypted storage is on (config `encrypt_images`)
+    passphrase: Option<String>,
+    overwrite: Option<bool>,
+    gray_levels: Option<u16>,
+    keep_original: Option<bool>,
+    enhance: Option<bool>,
+}
+
+/// Validate the optional JPEG quality (1-100, default 75)
+pub fn parse_quality(quality: Option<u8>) -> Result<u8, AppError> {
+    let quality = quality.unwrap_or(DEFAULT_JPEG_QUALITY);
+    if !(1..=100).contains(&quality) {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid JPEG quality: {} (expected 1-100)", quality),
+        });
+    }
+    Ok(quality)
+}
+
+/// Apply the optional longest-side limit (clamped to 256-4096, default 1536)
+fn parse_max_dimension(max_dimension: Option<u32>) -> u32 {
+    max_dimension.unwrap_or(DEFAULT_MAX_DIMENSION).clamp(MAX_DIMENSION_RANGE.0, MAX_DIMENSION_RANGE.1)
+}
+
+/// Validated compression settings shared by the single and batch commands
+struct CompressOptions {
+    quality: u8,
+    format: FormatSelection,
+    max_dimension: u32,
+    grayscale: bool,
+    verify_output: bool,
+    auto_webp_threshold: u64,
+    auto_include_png: bool,
+    /// Validated override for the images directory
+    output_dir: Option<PathBuf>,
+    /// fsync the output before reporting success
+    durable: bool,
+    /// Trim background margins before resizing
+    auto_crop: bool,
+    /// Never store the source unchanged
+    force_reencode: bool,
+    /// Set when encrypted storage is on; output goes to `{id}.enc`
+    encryption_key: Option<StorageKey>,
+    /// Replace an image already stored under the same id (otherwise AlreadyExists)
+    overwrite: bool,
+    /// Posterize the grayscale image to this many levels before encoding
+    gray_levels: Option<u16>,
+    /// Also store an untouched copy of the source as `{id}_original.{ext}`
+    keep_original: bool,
+    /// Stretch the contrast of faded (low-contrast) grayscale images before encoding
+    enhance: bool,
+}
+
+impl CompressOptions {
+    /// Validate the optional command arguments and apply defaults
+    fn from_args(args: Option<CompressArgs>) -> Result<Self, AppError> {
+        let args = args.unwrap_or_default();
+        let quality = parse_quality(args.quality)?;
+        let format = FormatSelection::parse(args.format.as_deref())?;
+        let max_dimension = parse_max_dimension(args.max_dimension);
+        let grayscale = args.grayscale.unwrap_or(true);
+        let gray_levels = args.gray_levels.filter(|&levels| levels != 256);
+        if let Some(levels) = gray_levels {
+            if !GRAY_LEVELS_RANGE.contains(&levels) {
+                return Err(AppError::InvalidArgument {
+                    reason: format!("Invalid grayLevels: {} (expected 4-256)", levels),
+                });
+            }
+            if !grayscale {
+                return Err(AppError::InvalidArgument {
+                    reason: "grayLevels requires grayscale output".to_string(),
+                });
+            }
+        }
+        let enhance = args.enhance.unwrap_or(false);
+        if enhance && !grayscale {
+            return Err(AppError::InvalidArgument { reason: "enhance requires grayscale output".to_string() });
+        }
+        let keep_original = args.keep_original.unwrap_or(false);
+        // Derived here so a batch pays the Argon2 cost once
+        let encryption_key = if crypto::encryption_enabled() {
+            let passphrase = args.passphrase.as_deref().ok_or_else(|| AppError::InvalidArgument {
+                reason: "Encrypted storage is enabled: a passphrase is required".to_string(),
+            })?;
+            if keep_original {
+                return Err(AppError::InvalidArgument {
+                    reason: "keepOriginal is not available with encrypted storage".to_string(),
+                });
+            }
+            Some(crypto::derive_key(passphrase)?)
+        } else {
+            None
+        };
+        Ok(CompressOptions {
+            quality,
+            format,
+            max_dimension,
+            grayscale,
+            verify_output: args.verify_output.unwrap_or(false),
+            auto_webp_threshold: args.auto_webp_threshold.unwrap_or(DEFAULT_AUTO_WEBP_THRESHOLD),
+            auto_include_png: args.auto_include_png.unwrap_or(false),
+            output_dir: args.output_dir.as_deref().map(resolve_output_dir).transpose()?,
+            durable: args.durable.unwrap_or(true),
+            auto_crop: args.auto_crop.unwrap_or(false),
+            force_reencode: args.force_reencode.unwrap_or(false),
+            encryption_key,
+            overwrite: args.overwrite.unwrap_or(true),
+            gray_levels,
+            keep_original,
+            enhance,
+        })
+    }
+}
+
+/// Compress an image: resize to max 1536px, convert to grayscale, JPEG 75%
+/// Grayscale conversion reduces file size by ~60% while maintaining OCR quality
+/// `options` (all optional):
+/// - `quality` overrides the JPEG/WebP quality (1-100, default 75)
+/// - `format` selects "jpeg" (default), "webp" or "png" output, or "auto" to keep
+///   the smallest of JPEG and WebP (plus PNG with `autoIncludePng`); WebP is not tried
+///   when the JPEG is under `autoWebpThreshold` bytes (default 50KB)
+/// - `maxDimension` overrides the longest-side limit (clamped to 256-4096, default 1536)
+/// - `grayscale: false` keeps color (e.g. color-coded receipts for manual review)
+/// - `verifyOutput: true` re-decodes the written file before reporting success
+/// - `outputDir` writes somewhere other than the images directory (must be under home)
+/// - `durable: false` skips the fsync after writing (faster, but not crash-safe)
+/// - `autoCrop: true` trims white margins around the content before resizing, so more
+///   of the 1536px budget goes to the receipt itself (reported as `crop_applied`)
+/// - `forceReencode: true` always re-encodes; otherwise an upright JPEG source of at most
+///   300KB that already fits (and is already grayscale, unless `grayscale: false`) is
+///   stored unchanged (`was_passthrough`)
+/// - `passphrase` is required when encrypted storage is on; the image is then written
+///   as `{id}.enc` (md5 and compressed_size still describe the unencrypted image)
+/// - `enhance: true` stretches the contrast of faded receipts (e.g. old thermal paper)
+///   before encoding; images that already have good contrast are left alone, so noise
+///   isn't amplified (reported as `enhanced`)
+/// - `grayLevels` (4-256) posterizes the grayscale image to that many levels before
+///   encoding; 16 keeps receipts OCR-legible at a noticeably smaller size
+/// - `keepOriginal: true` also stores the untouched source as `{id}_original.{ext}` next
+///   to the compressed image (checksum-verified, reported as `original_stored_path`);
+///   not available with encrypted storage
+/// - `overwrite: false` fails with ALREADY_EXISTS when an image with this id is already
+///   stored (any format); the default replaces it and logs a warning
+///
+/// `trace_id` ties the Rust-side log entries to the frontend operation
+/// Decode, resize and encode run on a blocking worker, so concurrent calls don't stall IPC
+#[tauri::command]
+#[tracing::instrument(
+    skip_all,
+    fields(trace_id = trace_id.as_deref().unwrap_or("no-trace"), image_id = %image_id),
+    err(Display)
+)]
+pub async fn compress_image(
+    input_path: String,
+    image_id: String,
+    options: Option<CompressArgs>,
+    trace_id: Option<String>,
+) -> Result<CompressResult, AppError> {
+    run_blocking(move || {
+        let options = CompressOptions::from_args(options)?;
+        compress_file(input_path, image_id, &options)
+    })
+    .await
+}
+
+/// Largest decoded input compress_image_bytes accepts (a 12MP HEIC/JPEG is well under)
+const MAX_INPUT_BYTES: usize = 50 * 1024 * 1024;
+
+/// `original_path` reported for in-memory inputs
+const BYTES_INPUT_PATH: &str = "<bytes>";
+
+/// Decode a base64 payload (optionally a `data:...;base64,` URL), enforcing MAX_INPUT_BYTES
+fn decode_input_base64(data_base64: &str) -> Result<Vec<u8>, AppError> {
+    use base64::Engine;
+
+    let payload = match data_base64.strip_prefix("data:") {
+        Some(url) => url.split_once(";base64,").map(|(_, data)| data).ok_or_else(|| {
+            AppError::InvalidArgument { reason: "Data URL is not base64-encoded".to_string() }
+        })?,
+        None => data_base64,
+    };
+    let payload = payload.trim();
+    // Reject before allocating: every 4 base64 characters decode to at most 3 bytes
+    if payload.len() / 4 * 3 > MAX_INPUT_BYTES + 3 {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Input too large (max {} bytes)", MAX_INPUT_BYTES),
+        });
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|e| AppError::InvalidArgument { reason: format!("Invalid base64 input: {}", e) })?;
+    if bytes.len() > MAX_INPUT_BYTES {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Input too large (max {} bytes)", MAX_INPUT_BYTES),
+        });
+    }
+    if bytes.is_empty() {
+        return Err(AppError::InvalidArgument { reason: "Input is empty".to_string() });
+    }
+    Ok(bytes)
+}
+
+/// Removes the staged input file however compression ends
+struct StagedInput(PathBuf);
+
+impl StagedInput {
+    fn new() -> Self {
+        StagedInput(std::env::temp_dir().join(format!("yorutsuke-input-{}", uuid::Uuid::new_v4())))
+    }
+}
+
+impl Drop for StagedInput {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).ok();
+    }
+}
+
+/// Compress an image passed as base64 bytes instead of a path (test harnesses,
+/// programmatic ingestion); same pipeline, `options` and output as compress_image
+/// The input (at most 50MB decoded) is staged in the temp directory for the
+/// path-based decoders and removed afterwards; `original_path` is reported as "<bytes>"
+#[tauri::command]
+#[tracing::instrument(
+    skip_all,
+    fields(trace_id = trace_id.as_deref().unwrap_or("no-trace"), image_id = %image_id),
+    err(Display)
+)]
+pub async fn compress_image_bytes(
+    data_base64: String,
+    image_id: String,
+    options: Option<CompressArgs>,
+    trace_id: Option<String>,
+) -> Result<CompressResult, AppError> {
+    run_blocking(move || {
+        validate_image_id(&image_id)?;
+        let options = CompressOptions::from_args(options)?;
+        let bytes = decode_input_base64(&data_base64)?;
+        drop(data_base64);
+
+        let staged = StagedInput::new();
+        fs::write(&staged.0, &bytes).map_err(|e| AppError::io("Failed to stage input", e))?;
+        drop(bytes);
+
+        let mut result = compress_file(staged.0.to_string_lossy().to_string(), image_id, &options)?;
+        result.original_path = BYTES_INPUT_PATH.to_string();
+        Ok(result)
+    })
+    .await
+}
+
+/// Store already-decoded (upright) images through the compress pipeline
+/// For derived images such as split_receipts crops; each is staged as a lossless BMP.
+/// `options` are parsed once for all images. Stops at the first failure and removes
+/// the outputs written so far, so callers never see a partial set
+pub fn compress_decoded(
+    images: &[(DynamicImage, String)],
+    original_path: &str,
+    options: Option<CompressArgs>,
+) -> Result<Vec<CompressResult>, AppError> {
+    let options = CompressOptions::from_args(options)?;
+    let mut results: Vec<CompressResult> = Vec::with_capacity(images.len());
+    for (img, image_id) in images {
+        let staged = StagedInput::new();
+        let compressed = DynamicImage::ImageRgb8(img.to_rgb8())
+            .save_with_format(&staged.0, image::ImageFormat::Bmp)
+            .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to stage image: {}", e) })
+            .and_then(|_| compress_file(staged.0.to_string_lossy().to_string(), image_id.clone(), &options));
+        match compressed {
+            Ok(mut result) => {
+                result.original_path = original_path.to_string();
+                results.push(result);
+            }
+            Err(e) => {
+                for written in &results {
+                    fs::remove_file(&written.output_path).ok();
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// Batch compression result
+/// `results[i]` and `errors[i]` correspond to `items[i]`; exactly one of them is set
+#[derive(serde::Serialize)]
+pub struct BatchCompressResult {
+    pub results: Vec<Option<CompressResult>>,
+    pub errors: Vec<Option<AppError>>,
+    /// Stopped early by cancel_compress_batch; items not started have neither a result nor an error
+    pub cancelled: bool,
+}
+
+/// Cancellation flags of running compress_images batches, by batch_id (managed Tauri state)
+#[derive(Default)]
+pub struct CompressBatches {
+    active: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl CompressBatches {
+    /// Track a new batch; the returned guard unregisters it when dropped
+    fn register(&self, batch_id: String) -> Result<BatchRegistration<'_>, AppError> {
+        let mut active = self.active.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if active.contains_key(&batch_id) {
+            return Err(AppError::InvalidArgument { reason: format!("Batch already running: {}", batch_id) });
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        active.insert(batch_id.clone(), cancel.clone());
+        Ok(BatchRegistration { batches: self, batch_id, cancel })
+    }
+
+    /// Flag a running batch to stop; false when no batch has this id
+    fn cancel(&self, batch_id: &str) -> bool {
+        let active = self.active.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match active.get(batch_id) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A registered batch; dropping it removes the token, so every exit path
+/// (finished, cancelled, error or panic) cleans up
+struct BatchRegistration<'a> {
+    batches: &'a CompressBatches,
+    batch_id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for BatchRegistration<'_> {
+    fn drop(&mut self) {
+        let mut active = self.batches.active.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        active.remove(&self.batch_id);
+    }
+}
+
+/// Progress payload emitted as `compress-progress` after each batch item finishes
+#[derive(Clone, serde::Serialize)]
+pub struct CompressProgress {
+    pub completed: usize,
+    pub total: usize,
+    pub current_id: String,
+}
+
+/// Compress many images in one IPC call, in parallel on a bounded thread pool
+/// Each item is `(input_path, image_id)`; a failing item does not fail the batch
+/// Emits `compress-progress` after every item, whether it succeeded or failed
+/// `options` are applied to every item (see compress_image)
+/// `max_concurrency` caps how many images are decoded at once (default: CPU count);
+/// each in-flight 12MP photo holds ~50MB of pixels, so lower it on memory-tight machines
+/// Results are in input order regardless of completion order
+/// With a `batch_id`, cancel_compress_batch stops the batch between images: images
+/// already written stay stored and the partial result has `cancelled: true`
+/// The batch runs on a blocking worker, so the window stays responsive (and a cancel
+/// request can be handled) while it is in progress
+#[tauri::command]
+#[tracing::instrument(
+    skip_all,
+    fields(trace_id = trace_id.as_deref().unwrap_or("no-trace"), count = items.len()),
+    err(Display)
+)]
+pub async fn compress_images(
+    app: AppHandle,
+    items: Vec<(String, String)>,
+    options: Option<CompressArgs>,
+    max_concurrency: Option<usize>,
+    batch_id: Option<String>,
+    trace_id: Option<String>,
+) -> Result<BatchCompressResult, AppError> {
+    run_blocking(move || {
+        let options = CompressOptions::from_args(options)?;
+        let threads = match max_concurrency {
+            Some(0) => {
+                return Err(AppError::InvalidArgument {
+                    reason: "Invalid max_concurrency: 0 (expected >= 1)".to_string(),
+                })
+            }
+            Some(threads) => threads,
+            None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+        let batches = app.state::<CompressBatches>();
+        compress_batch(&batches, items, &options, threads, batch_id, |progress| {
+            app.emit("compress-progress", progress).ok();
+        })
+    })
+    .await
+}
+
+/// Body of compress_images: compress `items` on a `threads`-wide pool, registering
+/// `batch_id` with `batches` for the duration so it can be cancelled
+/// `on_progress` is called after every finished item
+fn compress_batch(
+    batches: &CompressBatches,
+    items: Vec<(String, String)>,
+    options: &CompressOptions,
+    threads: usize,
+    batch_id: Option<String>,
+    on_progress: impl Fn(CompressProgress) + Sync,
+) -> Result<BatchCompressResult, AppError> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| AppError::InvalidArgument { reason: format!("Failed to start compression pool: {}", e) })?;
+    // Rayon workers don't inherit the span, so enter it explicitly per item
+    let span = tracing::Span::current();
+    let total = items.len();
+    let completed = AtomicUsize::new(0);
+    // Held until the batch returns, then unregistered
+    let registration = batch_id.map(|id| batches.register(id)).transpose()?;
+    let cancel = registration.as_ref().map_or_else(|| Arc::new(AtomicBool::new(false)), |r| r.cancel.clone());
+
+    // collect() on an indexed parallel iterator keeps input order
+    // None marks items skipped after cancellation
+    let outcomes: Vec<Option<Result<CompressResult, AppError>>> = pool.install(|| {
+        items
+            .into_par_iter()
+            .map(|(input_path, image_id)| {
+                if cancel.load(Ordering::SeqCst) {
+                    return None;
+                }
+                let outcome = span.in_scope(|| compress_file(input_path, image_id.clone(), options));
+                on_progress(CompressProgress {
+                    completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                    current_id: image_id,
+                });
+                Some(outcome)
+            })
+            .collect()
+    });
+
+    let cancelled = cancel.load(Ordering::SeqCst);
+    if cancelled {
+        tracing::info!(completed = completed.load(Ordering::SeqCst), total, "compress_batch_cancelled");
+    }
+
+    let mut results = Vec::with_capacity(outcomes.len());
+    let mut errors = Vec::with_capacity(outcomes.len());
+    for outcome in outcomes {
+        match outcome {
+            Some(Ok(result)) => {
+                results.push(Some(result));
+                errors.push(None);
+            }
+            Some(Err(e)) => {
+                results.push(None);
+                errors.push(Some(e));
+            }
+            None => {
+                results.push(None);
+                errors.push(None);
+            }
+        }
+    }
+
+    Ok(BatchCompressResult { results, errors, cancelled })
+}
+
+/// Ask the compress_images batch started with `batch_id` to stop after the images
+/// in flight; returns false when no such batch is running (e.g. it already finished)
+#[tauri::command]
+pub fn cancel_compress_batch(batches: State<'_, CompressBatches>, batch_id: String) -> bool {
+    let found = batches.cancel(&batch_id);
+    tracing::info!(batch_id = %batch_id, found, "compress_batch_cancel_requested");
+    found
+}
+
+/// Result of importing a source image into the store
+#[derive(serde::Serialize)]
+pub struct ImportResult {
+    pub path: String,
+    pub hash: String,
+    pub was_duplicate: bool,
+}
+
+/// Import a source image under an id derived from its MD5
+/// If an image is already stored under that hash (in any format, encrypted included)
+/// its path is returned instead of compressing again, so dedup is decided where the
+/// filesystem is authoritative
+/// `options` are the compress_image options (a `passphrase` is required when encrypted
+/// storage is on); `outputDir` also decides where duplicates are looked up
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(trace_id = trace_id.as_deref().unwrap_or("no-trace")), err(Display))]
+pub async fn import_image(
+    source_path: String,
+    options: Option<CompressArgs>,
+    trace_id: Option<String>,
+) -> Result<ImportResult, AppError> {
+    run_blocking(move || {
+        let options = CompressOptions::from_args(options)?;
+        let hash = hash_file(Path::new(&source_path), HashAlgorithm::Md5)?;
+        let data_dir = match &options.output_dir {
+            Some(dir) => dir.clone(),
+            None => get_data_dir()?,
+        };
+        if let Some(existing) = existing_output(&data_dir, &hash) {
+            return Ok(ImportResult {
+                path: existing.to_string_lossy().to_string(),
+                hash,
+                was_duplicate: true,
+            });
+        }
+
+        let result = compress_file(source_path, hash.clone(), &options)?;
+        Ok(ImportResult {
+            path: result.output_path,
+            hash,
+            was_duplicate: false,
+        })
+    })
+    .await
+}
+
+/// Encode `img` in `format` into `out`
+/// `quality` applies to JPEG and (lossy) WebP; PNG is always lossless
+fn encode_image<W: Write>(mut out: W, img: &DynamicImage, format: OutputFormat, quality: u8) -> Result<(), AppError> {
+    let (width, height) = img.dimensions();
+    match format {
+        OutputFormat::Jpeg => {
+            // Encode to JPEG (default 75% quality - balanced for OCR and file size)
+            let rgb_image = img.to_rgb8();
+            let mut encoder = JpegEncoder::new_with_quality(out, quality);
+            encoder.encode(&rgb_image, width, height, image::ExtendedColorType::Rgb8)
+                .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to encode JPEG: {}", e) })
+        }
+        OutputFormat::WebP => {
+            // Lossy through libwebp; grayscale goes in as luma, without an RGB copy
+            let encoded = webp::encode_lossy(img, quality)?;
+            out.write_all(&encoded)
+                .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to encode WebP: {}", e) })
+        }
+        OutputFormat::Png => {
+            // Lossless for sharp screenshot text; keeps alpha and stays single-channel when grayscale
+            let png_image = match (img.color().has_color(), img.color().has_alpha()) {
+                (false, false) => DynamicImage::ImageLuma8(img.to_luma8()),
+                (false, true) => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+                (true, false) => DynamicImage::ImageRgb8(img.to_rgb8()),
+                (true, true) => DynamicImage::ImageRgba8(img.to_rgba8()),
+            };
+            let encoder = PngEncoder::new_with_quality(out, CompressionType::Default, PngFilterType::Adaptive);
+            encoder
+                .write_image(png_image.as_bytes(), width, height, png_image.color().into())
+                .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to encode PNG: {}", e) })
+        }
+    }
+}
+
+/// Encode into memory, hashing the bytes as the encoder produces them
+fn encode_hashed(img: &DynamicImage, format: OutputFormat, quality: u8) -> Result<(Vec<u8>, String), AppError> {
+    let mut out = HashingWriter::new(Vec::new());
+    encode_image(&mut out, img, format, quality)?;
+    let (bytes, md5, _) = out.finish();
+    Ok((bytes, md5))
+}
+
+/// Encode straight into a new file at `path` through a HashingWriter, so the bytes are
+/// written and hashed in one pass; returns the MD5 and size
+/// A partially written file is removed on failure
+fn encode_to_file(
+    path: &Path,
+    img: &DynamicImage,
+    format: OutputFormat,
+    quality: u8,
+    durable: bool,
+) -> Result<(String, u64), AppError> {
+    let write = || {
+        let file = fs::File::create(path).map_err(|e| AppError::io("Failed to write output file", e))?;
+        let mut out = HashingWriter::new(BufWriter::new(file));
+        encode_image(&mut out, img, format, quality)?;
+        let (writer, md5, size) = out.finish();
+        let file = writer.into_inner().map_err(|e| AppError::io("Failed to write output file", e.into_error()))?;
+        if durable {
+            file.sync_all().map_err(|e| AppError::io("Failed to write output file", e))?;
+        }
+        Ok((md5, size))
+    };
+    write().inspect_err(|_| {
+        fs::remove_file(path).ok();
+    })
+}
+
+/// Encode every candidate format in memory and return the smallest with its MD5
+/// WebP is skipped when the JPEG already fits under `auto_webp_threshold`
+fn encode_smallest(img: &DynamicImage, options: &CompressOptions) -> Result<(OutputFormat, Vec<u8>, String), AppError> {
+    let (jpeg, md5) = encode_hashed(img, OutputFormat::Jpeg, options.quality)?;
+    let mut best = (OutputFormat::Jpeg, jpeg, md5);
+
+    let mut candidates = Vec::new();
+    if best.1.len() as u64 >= options.auto_webp_threshold {
+        candidates.push(OutputFormat::WebP);
+    }
+    if options.auto_include_png {
+        candidates.push(OutputFormat::Png);
+    }
+    for format in candidates {
+        let (encoded, md5) = encode_hashed(img, format, options.quality)?;
+        if encoded.len() < best.1.len() {
+            best = (format, encoded, md5);
+        }
+    }
+    Ok(best)
+}
+
+/// Encode the processed image in memory as selected by `options.format`, with its MD5
+/// Auto mode keeps only the smallest candidate, so no losing encodings reach disk
+fn encode_output(img: &DynamicImage, options: &CompressOptions) -> Result<(OutputFormat, Vec<u8>, String), AppError> {
+    match options.format {
+        FormatSelection::Fixed(format) => {
+            let (encoded, md5) = encode_hashed(img, format, options.quality)?;
+            Ok((format, encoded, md5))
+        }
+        FormatSelection::Auto => encode_smallest(img, options),
+    }
+}
+
+/// Re-decode a written image (decrypting it first with `key`) and confirm its dimensions
+fn verify_encoded(path: &Path, key: Option<&StorageKey>, width: u32, height: u32) -> Result<(), AppError> {
+    // Sniffed rather than taken from the extension, since a staged file ends in .tmp
+    let decoded = match key {
+        None => image::ImageReader::open(path)
+            .and_then(|reader| reader.with_guessed_format())
+            .map_err(|e| AppError::io_at(path, "Output verification failed", e))?
+            .decode(),
+        Some(key) => {
+            let data = fs::read(path).map_err(|e| AppError::io_at(path, "Output verification failed", e))?;
+            image::load_from_memory(&crypto::decrypt(key, &data)?)
+        }
+    }
+    .map_err(|e| AppError::DecodeFailed { reason: format!("Output verification failed: {}", e) })?;
+    if decoded.dimensions() != (width, height) {
+        return Err(AppError::DecodeFailed {
+            reason: format!(
+                "Output verification failed: expected {}x{}, decoded {}x{}",
+                width,
+                height,
+                decoded.width(),
+                decoded.height()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Bounding box of non-background content, padded by ~1% of the longest side
+/// Returns None when there's nothing to trim or the box is under 20% of the image,
+/// which usually means a faint receipt whose text fell under the threshold
+fn content_bounds(img: &DynamicImage) -> Option<CropRect> {
+    let luma = img.to_luma8();
+    let (width, height) = luma.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut row_ink = vec![0u32; height as usize];
+    let mut col_ink = vec![0u32; width as usize];
+    for (x, y, pixel) in luma.enumerate_pixels() {
+        if pixel.0[0] < CROP_INK_THRESHOLD {
+            row_ink[y as usize] += 1;
+            col_ink[x as usize] += 1;
+        }
+    }
+
+    // First and last index whose ink count reaches the minimum
+    let span = |ink: &[u32], across: u32| {
+        let min_ink = ((across as f64 * CROP_MIN_INK_FRACTION).ceil() as u32).max(1);
+        let first = ink.iter().position(|&count| count >= min_ink)?;
+        let last = ink.iter().rposition(|&count| count >= min_ink)?;
+        Some((first as u32, last as u32))
+    };
+    let (top, bottom) = span(&row_ink, width)?;
+    let (left, right) = span(&col_ink, height)?;
+
+    let padding = (width.max(height) / 100).max(8);
+    let x = left.saturating_sub(padding);
+    let y = top.saturating_sub(padding);
+    let crop = CropRect {
+        x,
+        y,
+        width: (right + padding + 1).min(width) - x,
+        height: (bottom + padding + 1).min(height) - y,
+    };
+
+    let area_fraction = (crop.width as f64 * crop.height as f64) / (width as f64 * height as f64);
+    if area_fraction < CROP_MIN_AREA_FRACTION || (crop.width, crop.height) == (width, height) {
+        return None;
+    }
+    Some(crop)
+}
+
+/// Write the encoded image, optionally syncing it to disk
+/// Stored images are the source of truth until cloud sync, so by default success
+/// is only reported once the bytes are durable
+fn write_output(path: &Path, bytes: &[u8], durable: bool) -> std::io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(bytes)?;
+    file.flush()?;
+    if durable {
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Copy the source to `{id}_original.{ext}` and check the copy's MD5 against the source
+/// A copy that doesn't match is removed; the extension follows the sniffed format
+fn store_original(
+    source: &Path,
+    source_format: SourceFormat,
+    data_dir: &Path,
+    image_id: &str,
+    durable: bool,
+) -> Result<PathBuf, AppError> {
+    let dest = data_dir.join(format!("{}{}.{}", image_id, ORIGINAL_SUFFIX, source_format.extension()));
+    fs::copy(source, &dest).map_err(|e| AppError::io("Failed to store original", e))?;
+    if durable {
+        fs::File::open(&dest)
+            .and_then(|file| file.sync_all())
+            .map_err(|e| AppError::io("Failed to store original", e))?;
+    }
+    let verified = hash_file(source, HashAlgorithm::Md5)? == hash_file(&dest, HashAlgorithm::Md5)?;
+    if !verified {
+        fs::remove_file(&dest).ok();
+        return Err(AppError::IoError { reason: "Stored original does not match the source".to_string() });
+    }
+    Ok(dest)
+}
+
+/// Dimensions after fitting the longest side within `max_size` (never upscaled)
+pub fn target_dimensions(width: u32, height: u32, max_size: u32) -> (u32, u32) {
+    if width > height {
+        if width > max_size {
+            let ratio = max_size as f32 / width as f32;
+            (max_size, (height as f32 * ratio) as u32)
+        } else {
+            (width, height)
+        }
+    } else if height > max_size {
+        let ratio = max_size as f32 / height as f32;
+        ((width as f32 * ratio) as u32, max_size)
+    } else {
+        (width, height)
+    }
+}
+
+/// Stored path for an image id in `format`
+fn output_path_for(data_dir: &Path, image_id: &str, format: OutputFormat) -> PathBuf {
+    data_dir.join(format!("{}.{}", image_id, format.extension()))
+}
+
+/// Every image stored under `image_id` in `data_dir`, one per output format present
+/// WebP comes first: next to a JPEG it is the migrated image (see migrate_store_to_webp)
+fn existing_outputs(data_dir: &Path, image_id: &str) -> Vec<PathBuf> {
+    [OutputFormat::WebP, OutputFormat::Jpeg, OutputFormat::Png]
+        .into_iter()
+        .map(|format| output_path_for(data_dir, image_id, format))
+        .chain(std::iter::once(data_dir.join(format!("{}.{}", image_id, ENCRYPTED_EXTENSION))))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// An image already stored under `image_id` in `data_dir`, in any output format
+fn existing_output(data_dir: &Path, image_id: &str) -> Option<PathBuf> {
+    existing_outputs(data_dir, image_id).into_iter().next()
+}
+
+/// Allowed `grayLevels` (256 is full depth, i.e. no posterizing)
+const GRAY_LEVELS_RANGE: std::ops::RangeInclusive<u16> = 4..=256;
+
+/// Quantize to `levels` evenly spaced gray values (0 and 255 are always kept)
+/// Flattening sensor noise on the paper is where JPEG saves most of the bytes
+fn posterize(img: &mut image::GrayImage, levels: u16) {
+    let steps = u32::from(levels - 1);
+    for pixel in img.pixels_mut() {
+        let level = (u32::from(pixel.0[0]) * steps + 127) / 255;
+        pixel.0[0] = ((level * 255 + steps / 2) / steps) as u8;
+    }
+}
+
+/// Percentiles of the histogram treated as black and white by `enhance`
+/// (ignoring the extreme 1% on each side keeps specks and glare from setting the range)
+const ENHANCE_LOW_PERCENTILE: f64 = 0.01;
+const ENHANCE_HIGH_PERCENTILE: f64 = 0.99;
+
+/// Images whose percentile range already spans this many gray values are left alone
+/// A crisp receipt photo spans ~200; faded thermal paper often under 100
+const ENHANCE_MIN_SPREAD: u8 = 160;
+
+/// Below this spread the image is nearly uniform (a blank page has nothing to bring out)
+const ENHANCE_MIN_UNIFORM_SPREAD: u8 = 8;
+
+/// Gray values at the given cumulative fractions of the histogram
+fn histogram_percentiles(img: &image::GrayImage, low: f64, high: f64) -> (u8, u8) {
+    let mut histogram = [0u64; 256];
+    for pixel in img.pixels() {
+        histogram[usize::from(pixel.0[0])] += 1;
+    }
+    let total = img.pixels().len() as f64;
+    let find = |fraction: f64| {
+        let target = (total * fraction).ceil().max(1.0) as u64;
+        let mut seen = 0u64;
+        for (value, &count) in histogram.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return value as u8;
+            }
+        }
+        255
+    };
+    (find(low), find(high))
+}
+
+/// Linearly stretch a low-contrast image so its 1st-99th percentile range covers 0-255
+/// Returns false (image untouched) when the range is already wide or nearly empty
+fn enhance_contrast(img: &mut image::GrayImage) -> bool {
+    if img.pixels().len() == 0 {
+        return false;
+    }
+    let (low, high) = histogram_percentiles(img, ENHANCE_LOW_PERCENTILE, ENHANCE_HIGH_PERCENTILE);
+    let spread = high.saturating_sub(low);
+    if !(ENHANCE_MIN_UNIFORM_SPREAD..ENHANCE_MIN_SPREAD).contains(&spread) {
+        return false;
+    }
+    let (low, spread) = (f32::from(low), f32::from(spread));
+    let mut lut = [0u8; 256];
+    for (value, out) in lut.iter_mut().enumerate() {
+        *out = ((value as f32 - low) * 255.0 / spread).round().clamp(0.0, 255.0) as u8;
+    }
+    for pixel in img.pixels_mut() {
+        pixel.0[0] = lut[usize::from(pixel.0[0])];
+    }
+    true
+}
+
+/// Where the encode stage left the output
+enum EncodedData {
+    /// In memory, still to be written (or encrypted first)
+    Memory(Vec<u8>),
+    /// Already streamed to its final path
+    Written(PathBuf),
+}
+
+/// Output of the decode/encode stage
+struct EncodedImage {
+    format: OutputFormat,
+    data: EncodedData,
+    /// MD5 and size of the unencrypted encoded image
+    md5: String,
+    size: u64,
+    width: u32,
+    height: u32,
+    grayscale: bool,
+    crop_applied: Option<CropRect>,
+    was_passthrough: bool,
+    enhanced: bool,
+    timings: PhaseTimings,
+}
+
+/// Sources at most this size (and within max_dimension) may be stored unchanged
+const PASSTHROUGH_MAX_BYTES: u64 = 300 * 1024;
+
+/// The source bytes as-is when re-encoding wouldn't help: an upright JPEG of at most
+/// 300KB that already fits max_dimension, with JPEG output, no crop, no posterizing, no
+/// enhance and no forceReencode
+/// Only the header is read to decide; with grayscale output the source must already be
+/// grayscale, since the stored file keeps the source's colors (and metadata)
+fn passthrough_source(
+    path: &Path,
+    source_format: SourceFormat,
+    original_size: u64,
+    options: &CompressOptions,
+) -> Result<Option<EncodedImage>, AppError> {
+    let eligible = !options.force_reencode
+        && !options.auto_crop
+        && options.gray_levels.is_none()
+        && !options.enhance
+        && matches!(options.format, FormatSelection::Fixed(OutputFormat::Jpeg))
+        && matches!(source_format, SourceFormat::Image(image::ImageFormat::Jpeg))
+        && original_size <= PASSTHROUGH_MAX_BYTES;
+    if !eligible {
+        return Ok(None);
+    }
+
+    let started = Instant::now();
+    let mut decoder = source_format
+        .reader(path)?
+        .into_decoder()
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to read image header: {}", e) })?;
+    let (width, height) = decoder.dimensions();
+    // A copied file would keep its EXIF rotation, and not every consumer applies it
+    let upright = matches!(decoder.orientation(), Ok(Orientation::NoTransforms) | Err(_));
+    let grayscale = !decoder.color_type().has_color();
+    if width.max(height) > options.max_dimension || !upright || (options.grayscale && !grayscale) {
+        return Ok(None);
+    }
+    let mut out = HashingWriter::new(Vec::with_capacity(original_size as usize));
+    fs::File::open(path)
+        .and_then(|mut file| std::io::copy(&mut file, &mut out))
+        .map_err(|e| AppError::io_at(path, "Failed to read image", e))?;
+    let (bytes, md5, size) = out.finish();
+    Ok(Some(EncodedImage {
+        format: OutputFormat::Jpeg,
+        data: EncodedData::Memory(bytes),
+        md5,
+        size,
+        width,
+        height,
+        grayscale,
+        crop_applied: None,
+        was_passthrough: true,
+        enhanced: false,
+        timings: PhaseTimings { decode: elapsed_ms(started), ..PhaseTimings::default() },
+    }))
+}
+
+/// Decode, orient, crop, resize, grayscale and encode `path`
+/// With `stream_to` and a fixed output format, the encoder writes straight into that
+/// file; otherwise the result stays in memory (auto format picks among candidates)
+fn encode_source(
+    path: &Path,
+    source_format: SourceFormat,
+    options: &CompressOptions,
+    stream_to: Option<&Path>,
+) -> Result<EncodedImage, AppError> {
+    let mut timings = PhaseTimings::default();
+
+    // Load image (rotated/flipped upright according to EXIF orientation)
+    let phase = Instant::now();
+    let img = open_oriented(path, source_format)?;
+    timings.decode = elapsed_ms(phase);
+
+    // Trim scanner margins first so the pixel budget goes to the receipt
+    let phase = Instant::now();
+    let crop_applied = if options.auto_crop { content_bounds(&img) } else { None };
+    let img = match crop_applied {
+        Some(crop) => img.crop_imm(crop.x, crop.y, crop.width, crop.height),
+        None => img,
+    };
+
+    let (orig_width, orig_height) = img.dimensions();
+
+    // Calculate new dimensions (max 1536px on longest side by default, never upscaled)
+    let max_size = options.max_dimension;
+    let (new_width, new_height) = target_dimensions(orig_width, orig_height, max_size);
+
+    // Resize if needed
+    let resized = if new_width != orig_width || new_height != orig_height {
+        img.resize_exact(new_width, new_height, image::imageops::FilterType::Lanczos3)
+    } else {
+        img
+    };
+    timings.resize = elapsed_ms(phase);
+
+    // Convert to grayscale (unless disabled)
+    // Grayscale reduces file size significantly while maintaining OCR quality
+    let phase = Instant::now();
+    let processed = if options.grayscale { resized.grayscale() } else { resized };
+    let mut enhanced = false;
+    let processed = if options.enhance || options.gray_levels.is_some() {
+        let mut luma = processed.into_luma8();
+        // Stretch before posterizing, so the levels are spread over the full range
+        if options.enhance {
+            enhanced = enhance_contrast(&mut luma);
+        }
+        if let Some(levels) = options.gray_levels {
+            posterize(&mut luma, levels);
+        }
+        DynamicImage::ImageLuma8(luma)
+    } else {
+        processed
+    };
+    timings.grayscale = elapsed_ms(phase);
+
+    // Get actual dimensions
+    let (actual_width, actual_height) = processed.dimensions();
+
+    // The MD5 is taken as the encoder writes, so the output is never read back
+    let phase = Instant::now();
+    let (output_format, data, md5, size) = match (options.format, stream_to) {
+        (FormatSelection::Fixed(format), Some(out_path)) => {
+            let (md5, size) = encode_to_file(out_path, &processed, format, options.quality, options.durable)?;
+            (format, EncodedData::Written(out_path.to_path_buf()), md5, size)
+        }
+        _ => {
+            let (format, bytes, md5) = encode_output(&processed, options)?;
+            let size = bytes.len() as u64;
+            (format, EncodedData::Memory(bytes), md5, size)
+        }
+    };
+    timings.encode = elapsed_ms(phase);
+
+    Ok(EncodedImage {
+        format: output_format,
+        data,
+        md5,
+        size,
+        width: actual_width,
+        height: actual_height,
+        grayscale: options.grayscale,
+        crop_applied,
+        was_passthrough: false,
+        enhanced,
+        timings,
+    })
+}
+
+/// Shared compression pipeline: decode, orient, resize, grayscale, encode, hash
+fn compress_file(
+    input_path: String,
+    image_id: String,
+    options: &CompressOptions,
+) -> Result<CompressResult, AppError> {
+    let started = Instant::now();
+    // The id becomes a filename, so check it before touching the filesystem
+    validate_image_id(&image_id)?;
+    let quality = options.quality;
+
+    let path = Path::new(&input_path);
+    if !path.exists() {
+        return Err(AppError::FileNotFound { path: input_path });
+    }
+
+    // Reject non-images (PDF, mislabeled files) up front with a friendly error
+    let source_format = sniff_format(path)?;
+
+    // Get original file size
+    let original_size = fs::metadata(path)
+        .map_err(|e| AppError::io("Failed to read metadata", e))?
+        .len();
+
+    let data_dir = match &options.output_dir {
+        Some(dir) => dir.clone(),
+        None => get_data_dir()?,
+    };
+
+    // Same id twice is usually a frontend bug; checked before the expensive decode
+    let existing = existing_output(&data_dir, &image_id);
+    if let Some(existing) = &existing {
+        if !options.overwrite {
+            return Err(AppError::AlreadyExists { path: existing.to_string_lossy().to_string() });
+        }
+        tracing::warn!(image_id = %image_id, path = %existing.display(), "compress_overwrite");
+    }
+
+    // Refuse to start on a nearly-full disk instead of writing a truncated file
+    // The source size is a conservative upper bound for the compressed output
+    let needed = if options.keep_original { original_size * 2 } else { original_size };
+    ensure_free_space(&data_dir, needed)?;
+
+    // Plain output of a known format goes straight from the encoder to disk; an image
+    // being overwritten is encoded in memory first so a failed encode leaves it intact
+    let stream_to = match (options.format, &options.encryption_key, &existing) {
+        (FormatSelection::Fixed(format), None, None) => Some(output_path_for(&data_dir, &image_id, format)),
+        _ => None,
+    };
+
+    // Small JPEGs that already fit gain little from re-encoding and could lose quality
+    let encoded = match passthrough_source(path, source_format, original_size, options)? {
+        Some(encoded) => encoded,
+        None => encode_source(path, source_format, options, stream_to.as_deref())?,
+    };
+    let (actual_width, actual_height) = (encoded.width, encoded.height);
+    let output_format = encoded.format;
+    let (output_path, staged) = match &encoded.data {
+        EncodedData::Written(path) => (path.clone(), None),
+        EncodedData::Memory(bytes) => {
+            let (output_path, file_bytes) = match &options.encryption_key {
+                Some(key) => (
+                    data_dir.join(format!("{}.{}", image_id, ENCRYPTED_EXTENSION)),
+                    Cow::Owned(crypto::encrypt(key, bytes)?),
+                ),
+                None => (output_path_for(&data_dir, &image_id, output_format), Cow::Borrowed(bytes.as_slice())),
+            };
+            // An overwritten image is staged next to the old one and only renamed into
+            // place once the checks below pass, so any failure keeps the old image
+            if existing.is_some() {
+                let staged = write_staged(&output_path, &file_bytes)
+                    .map_err(|e| AppError::io("Failed to write output file", e))?;
+                (output_path, Some(staged))
+            } else {
+                write_output(&output_path, &file_bytes, options.durable)
+                    .map_err(|e| AppError::io("Failed to write output file", e))?;
+                (output_path, None)
+            }
+        }
+    };
+
+    let written = staged.as_deref().unwrap_or(&output_path);
+    let checked = (|| {
+        // Optional safety net for flaky storage: the file must decode at the expected size
+        if options.verify_output {
+            verify_encoded(written, options.encryption_key.as_ref(), actual_width, actual_height)?;
+        }
+        if options.keep_original {
+            let stored = store_original(path, source_format, &data_dir, &image_id, options.durable)?;
+            return Ok(Some(stored.to_string_lossy().to_string()));
+        }
+        Ok(None)
+    })();
+    let original_stored_path = match checked {
+        Ok(stored) => stored,
+        Err(e) => {
+            fs::remove_file(written).ok();
+            return Err(e);
+        }
+    };
+    if let Some(staged) = &staged {
+        commit_staged(staged, &output_path).map_err(|e| AppError::io("Failed to write output file", e))?;
+    }
+
+    // The new output replaces the old one even when the format (or encryption) changed,
+    // e.g. no plaintext `{id}.jpg` is left next to a new `{id}.enc`
+    if existing.is_some() {
+        for stale in existing_outputs(&data_dir, &image_id).into_iter().filter(|p| *p != output_path) {
+            if let Err(e) = fs::remove_file(&stale) {
+                tracing::warn!(image_id = %image_id, path = %stale.display(), error = %e, "compress_overwrite_cleanup_failed");
+            }
+        }
+    }
+
+    // MD5 of the compressed data (for duplicate detection), taken while encoding
+    let md5_hash = encoded.md5;
+    let mut timings = encoded.timings;
+    let compressed_size = encoded.size;
+    let output_path_str = output_path.to_string_lossy().to_string();
+
+    // Lossless output (PNG in particular) can end up bigger than the source
+    if compressed_size > original_size {
+        tracing::warn!(
+            image_id = %image_id,
+            format = output_format.name(),
+            original_size,
+            compressed_size,
+            "compress_larger_than_original"
+        );
+    }
+
+    timings.total = elapsed_ms(started);
+    tracing::debug!(
+        image_id = %image_id,
+        original_size,
+        decode_ms = timings.decode,
+        resize_ms = timings.resize,
+        grayscale_ms = timings.grayscale,
+        encode_ms = timings.encode,
+        hash_ms = timings.hash,
+        total_ms = timings.total,
+        "compress_timings"
+    );
+
+    Ok(CompressResult {
+        success: true,
+        id: image_id,
+        original_path: input_path,
+        output_path: output_path_str,
+        original_size,
+        compressed_size,
+        width: actual_width,
+        height: actual_height,
+        md5: md5_hash,
+        quality_used: (!encoded.was_passthrough).then_some(quality),
+        max_dimension_used: options.max_dimension,
+        grayscale: encoded.grayscale,
+        chosen_format: output_format.name().to_string(),
+        crop_applied: encoded.crop_applied,
+        was_passthrough: encoded.was_passthrough,
+        timings_ms: timings,
+        gray_levels_used: if encoded.was_passthrough { None } else { options.gray_levels },
+        original_stored_path,
+        enhanced: encoded.enhanced,
+    })
+}
+
+/// Empirical JPEG bytes per output pixel for grayscale receipt photos:
+/// roughly `BASE + PER_QUALITY * quality` (about 0.15 at the default quality of 75)
+const ESTIMATE_BPP_BASE: f64 = 0.04;
+const ESTIMATE_BPP_PER_QUALITY: f64 = 0.0015;
+
+/// Color JPEGs come out larger than grayscale by about this factor
+const ESTIMATE_COLOR_FACTOR: f64 = 1.5;
+
+/// Rough output size for compress_image, without decoding pixels
+#[derive(serde::Serialize)]
+pub struct SizeEstimate {
+    pub estimated_bytes: u64,
+    /// Dimensions after resizing
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Estimate the compressed JPEG size from the header dimensions alone
+/// Uses the same resize rules and `options` as compress_image (quality, maxDimension,
+/// grayscale); the estimate is a heuristic, typically within ±30% for receipt photos
+/// and less reliable for unusually busy or blank images
+/// Only those three options are read, so no passphrase is needed with encrypted storage
+#[tauri::command]
+pub fn estimate_compressed_size(
+    input_path: String,
+    options: Option<CompressArgs>,
+) -> Result<SizeEstimate, AppError> {
+    let options = options.unwrap_or_default();
+    let quality = parse_quality(options.quality)?;
+    let max_dimension = parse_max_dimension(options.max_dimension);
+    let grayscale = options.grayscale.unwrap_or(true);
+    let path = Path::new(&input_path);
+    let format = sniff_format(path)?;
+    let decoder = format
+        .reader(path)?
+        .into_decoder()
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to read image header: {}", e) })?;
+    let (orig_width, orig_height) = decoder.dimensions();
+    let (width, height) = target_dimensions(orig_width, orig_height, max_dimension);
+
+    let mut bytes_per_pixel = ESTIMATE_BPP_BASE + ESTIMATE_BPP_PER_QUALITY * f64::from(quality);
+    if !grayscale {
+        bytes_per_pixel *= ESTIMATE_COLOR_FACTOR;
+    }
+    let pixels = u64::from(width) * u64::from(height);
+    Ok(SizeEstimate {
+        estimated_bytes: (pixels as f64 * bytes_per_pixel).round() as u64,
+        width,
+        height,
+    })
+}
+
+/// Largest stored image read_image_base64 will inline (base64 adds another third)
+const MAX_INLINE_IMAGE_BYTES: u64 = 15 * 1024 * 1024;
+
+/// A stored image encoded for upload
+#[derive(serde::Serialize)]
+pub struct ImageDataUrl {
+    /// `data:{mime};base64,...`
+    pub data_url: String,
+    /// Size of the raw image bytes (before base64)
+    pub byte_len: u64,
+    pub mime: String,
+}
+
+/// Read a stored `{id}.{ext}` as a base64 data URL for the sync layer
+/// `format` is "jpeg" (default), "webp" or "png"; files over 15MB are rejected
+#[tauri::command]
+pub fn read_image_base64(image_id: String, format: Option<String>) -> Result<ImageDataUrl, AppError> {
+    validate_image_id(&image_id)?;
+    let format = match FormatSelection::parse(format.as_deref())? {
+        FormatSelection::Fixed(format) => format,
+        FormatSelection::Auto => return Err(AppError::UnsupportedFormat { format: "auto".to_string() }),
+    };
+    let bytes = read_inline_file(&output_path_for(&get_data_dir()?, &image_id, format))?;
+    Ok(to_data_url(&bytes, format.mime()))
+}
+
+/// Decrypt a stored `{id}.enc` in memory and return it as a base64 data URL for display
+/// The plaintext never touches disk; a wrong passphrase fails with DECRYPT_FAILED
+#[tauri::command]
+pub fn read_image_encrypted(image_id: String, passphrase: String) -> Result<ImageDataUrl, AppError> {
+    validate_image_id(&image_id)?;
+    let path = get_data_dir()?.join(format!("{}.{}", image_id, ENCRYPTED_EXTENSION));
+    let data = read_inline_file(&path)?;
+    let bytes = crypto::decrypt(&crypto::derive_key(&passphrase)?, &data)?;
+    let mime = infer::get(&bytes)
+        .map(|kind| kind.mime_type())
+        .ok_or_else(|| AppError::DecodeFailed { reason: "Decrypted data is not an image".to_string() })?;
+    Ok(to_data_url(&bytes, mime))
+}
+
+/// Read a stored file for inlining, rejecting anything over MAX_INLINE_IMAGE_BYTES
+fn read_inline_file(path: &Path) -> Result<Vec<u8>, AppError> {
+    let byte_len = fs::metadata(path)
+        .map_err(|e| AppError::io_at(path, "Failed to read metadata", e))?
+        .len();
+    if byte_len > MAX_INLINE_IMAGE_BYTES {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Image too large to inline: {} bytes (max {})", byte_len, MAX_INLINE_IMAGE_BYTES),
+        });
+    }
+    fs::read(path).map_err(|e| AppError::io_at(path, "Failed to read image", e))
+}
+
+fn to_data_url(bytes: &[u8], mime: &str) -> ImageDataUrl {
+    use base64::Engine;
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+    ImageDataUrl {
+        data_url: format!("data:{};base64,{}", mime, encoded),
+        byte_len: bytes.len() as u64,
+        mime: mime.to_string(),
+    }
+}
+
+/// Result of rotating a stored image (bytes changed, so the hash did too)
+#[derive(serde::Serialize)]
+pub struct RotateResult {
+    pub md5: String,
+    pub width: u32,
+    pub height: u32,
+    pub compressed_size: u64,
+}
+
+/// Decode a JPEG, rotate it and re-encode it in memory (the file is not touched)
+fn rotate_jpeg(path: &Path, degrees: u32, quality: u8) -> Result<(DynamicImage, Vec<u8>), AppError> {
+    let img = image::open(path)
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to open image: {}", e) })?;
+    let rotated = match degrees {
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        _ => img.rotate270(),
+    };
+    let mut encoded = Vec::new();
+    encode_image(&mut encoded, &rotated, OutputFormat::Jpeg, quality)?;
+    Ok((rotated, encoded))
+}
+
+/// Rotate a stored `{id}.jpg` clockwise by 90, 180 or 270 degrees and overwrite it
+/// `quality` should be the `quality_used` from the original compress (default 75);
+/// an existing `{id}_thumb.jpg` is rotated to match
+/// Both files are encoded and staged before either is replaced, so a failure leaves
+/// the id as it was rather than half-rotated; runs on a blocking worker
+#[tauri::command]
+#[tracing::instrument(
+    skip_all,
+    fields(trace_id = trace_id.as_deref().unwrap_or("no-trace"), image_id = %image_id, degrees),
+    err(Display)
+)]
+pub async fn rotate_stored_image(
+    image_id: String,
+    degrees: u32,
+    quality: Option<u8>,
+    trace_id: Option<String>,
+) -> Result<RotateResult, AppError> {
+    validate_image_id(&image_id)?;
+    if !matches!(degrees, 90 | 180 | 270) {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid rotation: {} (expected 90, 180 or 270)", degrees),
+        });
+    }
+    let quality = parse_quality(quality)?;
+
+    run_blocking(move || {
+        let data_dir = get_data_dir()?;
+        rotate_stored_in(&data_dir, &image_id, degrees, quality)
+    })
+    .await
+}
+
+/// rotate_stored_image against `data_dir`
+fn rotate_stored_in(data_dir: &Path, image_id: &str, degrees: u32, quality: u8) -> Result<RotateResult, AppError> {
+    let path = output_path_for(data_dir, image_id, OutputFormat::Jpeg);
+    if !path.exists() {
+        return Err(AppError::FileNotFound { path: path.to_string_lossy().to_string() });
+    }
+    let thumb_path = data_dir.join(format!("{}_thumb.jpg", image_id));
+    let (rotated, encoded) = rotate_jpeg(&path, degrees, quality)?;
+    let thumb = if thumb_path.exists() { Some(rotate_jpeg(&thumb_path, degrees, quality)?.1) } else { None };
+
+    let staged = write_staged(&path, &encoded).map_err(|e| AppError::io("Failed to write rotated image", e))?;
+    let staged_thumb = match thumb.as_deref().map(|thumb| write_staged(&thumb_path, thumb)).transpose() {
+        Ok(staged_thumb) => staged_thumb,
+        Err(e) => {
+            fs::remove_file(&staged).ok();
+            return Err(AppError::io("Failed to write rotated thumbnail", e));
+        }
+    };
+    if let Err(e) = commit_staged(&staged, &path) {
+        if let Some(staged_thumb) = &staged_thumb {
+            fs::remove_file(staged_thumb).ok();
+        }
+        return Err(AppError::io("Failed to write rotated image", e));
+    }
+    if let Some(staged_thumb) = &staged_thumb {
+        if let Err(e) = commit_staged(staged_thumb, &thumb_path) {
+            // The image is already rotated; no thumbnail beats one facing the wrong way
+            tracing::warn!(image_id = %image_id, error = %e, "rotate_thumbnail_replace_failed");
+            fs::remove_file(&thumb_path).ok();
+        }
+    }
+
+    Ok(RotateResult {
+        md5: format!("{:x}", md5::compute(&encoded)),
+        width: rotated.width(),
+        height: rotated.height(),
+        compressed_size: encoded.len() as u64,
+    })
+}
+
+/// Result of re-encoding a stored image at a new quality
+#[derive(serde::Serialize)]
+pub struct RecompressResult {
+    pub id: String,
+    pub old_size: u64,
+    pub new_size: u64,
+    /// False when the new encoding wasn't smaller and the original was kept
+    pub replaced: bool,
+    /// MD5 of the file now on disk (unchanged when not replaced)
+    pub md5: String,
+}
+
+/// Re-encode `{id}.jpg` at `quality`, replacing it only when that saves bytes
+fn recompress_file(image_id: &str, quality: u8) -> Result<RecompressResult, AppError> {
+    validate_image_id(image_id)?;
+    let path = output_path_for(&get_data_dir()?, image_id, OutputFormat::Jpeg);
+    if !path.exists() {
+        return Err(AppError::FileNotFound { path: path.to_string_lossy().to_string() });
+    }
+
+    let original = fs::read(&path).map_err(|e| AppError::io_at(&path, "Failed to read image", e))?;
+    let img = image::load_from_memory(&original)
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to open image: {}", e) })?;
+    let mut encoded = Vec::new();
+    encode_image(&mut encoded, &img, OutputFormat::Jpeg, quality)?;
+
+    let replaced = encoded.len() < original.len();
+    if replaced {
+        write_atomic(&path, &encoded).map_err(|e| AppError::io("Failed to write recompressed image", e))?;
+    }
+    let kept = if replaced { &encoded } else { &original };
+    Ok(RecompressResult {
+        id: image_id.to_string(),
+        old_size: original.len() as u64,
+        new_size: kept.len() as u64,
+        replaced,
+        md5: format!("{:x}", md5::compute(kept)),
+    })
+}
+
+/// Shrink an already-stored `{id}.jpg` by re-encoding it at `quality` (1-100)
+/// For images stored by older builds at a higher quality; the file is only overwritten
+/// (atomically) when the new encoding is smaller, otherwise it's left untouched
+#[tauri::command]
+#[tracing::instrument(
+    skip_all,
+    fields(trace_id = trace_id.as_deref().unwrap_or("no-trace"), image_id = %image_id, quality),
+    err(Display)
+)]
+pub async fn recompress_stored_image(
+    image_id: String,
+    quality: u8,
+    trace_id: Option<String>,
+) -> Result<RecompressResult, AppError> {
+    let quality = parse_quality(Some(quality))?;
+    run_blocking(move || recompress_file(&image_id, quality)).await
+}
+
+/// Batch recompression result
+/// `results[i]` and `errors[i]` correspond to `image_ids[i]`; exactly one of them is set
+#[derive(serde::Serialize)]
+pub struct BatchRecompressResult {
+    pub results: Vec<Option<RecompressResult>>,
+    pub errors: Vec<Option<AppError>>,
+}
+
+/// Recompress many stored images in parallel (see recompress_stored_image)
+/// A failing image does not fail the batch
+#[tauri::command]
+#[tracing::instrument(
+    skip_all,
+    fields(trace_id = trace_id.as_deref().unwrap_or("no-trace"), count = image_ids.len(), quality),
+    err(Display)
+)]
+pub async fn recompress_stored_images(
+    image_ids: Vec<String>,
+    quality: u8,
+    trace_id: Option<String>,
+) -> Result<BatchRecompressResult, AppError> {
+    let quality = parse_quality(Some(quality))?;
+    run_blocking(move || {
+        let span = tracing::Span::current();
+        let outcomes: Vec<Result<RecompressResult, AppError>> = image_ids
+            .par_iter()
+            .map(|image_id| span.in_scope(|| recompress_file(image_id, quality)))
+            .collect();
+        let (results, errors) = outcomes
+            .into_iter()
+            .map(|outcome| match outcome {
+                Ok(result) => (Some(result), None),
+                Err(e) => (None, Some(e)),
+            })
+            .unzip();
+        Ok(BatchRecompressResult { results, errors })
+    })
+    .await
+}
+
+/// What migrate_store_to_webp did with one `{id}.jpg`
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebpMigrationStatus {
+    /// `{id}.webp` written (or would be, with `dry_run`)
+    Migrated,
+    /// `{id}.webp` already exists
+    AlreadyMigrated,
+    /// The WebP wasn't smaller, so the JPEG stays the only copy
+    NotSmaller,
+    /// The JPEG couldn't be decoded or the WebP couldn't be written (or an existing WebP
+    /// doesn't decode); the JPEG is untouched
+    Failed,
+}
+
+/// Per-file outcome of migrate_store_to_webp
+#[derive(serde::Serialize)]
+pub struct WebpMigrationFile {
+    pub id: String,
+    pub status: WebpMigrationStatus,
+    pub old_size: u64,
+    /// Size of the WebP (None when not encoded)
+    pub new_size: Option<u64>,
+    /// Set when `status` is failed
+    pub error: Option<AppError>,
+}
+
+/// Result of migrate_store_to_webp
+#[derive(serde::Serialize)]
+pub struct WebpMigrationResult {
+    pub files: Vec<WebpMigrationFile>,
+    /// Sum of `old_size - new_size` over migrated files; only reclaimed on disk once the
+    /// JPEGs are deleted
+    pub saved_bytes: u64,
+    /// JPEGs deleted (0 unless `delete_originals`)
+    pub deleted_count: u32,
+    pub dry_run: bool,
+}
+
+/// Check that an already stored `{id}.webp` fully decodes at the JPEG's dimensions,
+/// so deleting the JPEG never leaves a truncated or corrupt file as the only copy
+fn check_existing_webp(webp_path: &Path, jpeg_path: &Path) -> Result<(), AppError> {
+    let expected = image::image_dimensions(jpeg_path)
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to read image header: {}", e) })?;
+    let webp = image::open(webp_path)
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Stored WebP does not decode: {}", e) })?;
+    if webp.dimensions() != expected {
+        return Err(AppError::DecodeFailed {
+            reason: format!(
+                "Stored WebP is {}x{}, expected {}x{}",
+                webp.width(),
+                webp.height(),
+                expected.0,
+                expected.1
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Migrate one stored `{id}.jpg` to `{id}.webp`
+fn migrate_file_to_webp(dir: &Path, image_id: &str, delete_original: bool, dry_run: bool) -> WebpMigrationFile {
+    let jpeg_path = output_path_for(dir, image_id, OutputFormat::Jpeg);
+    let webp_path = output_path_for(dir, image_id, OutputFormat::WebP);
+    let mut file = WebpMigrationFile {
+        id: image_id.to_string(),
+        status: WebpMigrationStatus::Failed,
+        old_size: fs::metadata(&jpeg_path).map_or(0, |m| m.len()),
+        new_size: None,
+        error: None,
+    };
+    if webp_path.exists() {
+        file.new_size = fs::metadata(&webp_path).ok().map(|m| m.len());
+        // Kept JPEG from an earlier run without delete_originals: only removed once the
+        // WebP is known to be good
+        if delete_original {
+            if let Err(e) = check_existing_webp(&webp_path, &jpeg_path) {
+                tracing::warn!(image_id = %image_id, error = %e, "webp_migration_existing_invalid");
+                file.error = Some(e);
+                return file;
+            }
+            if !dry_run {
+                if let Err(e) = fs::remove_file(&jpeg_path) {
+                    file.error = Some(AppError::io_at(&jpeg_path, "Failed to remove migrated JPEG", e));
+                    return file;
+                }
+            }
+        }
+        file.status = WebpMigrationStatus::AlreadyMigrated;
+        return file;
+    }
+
+    let encoded = fs::read(&jpeg_path)
+        .map_err(|e| AppError::io_at(&jpeg_path, "Failed to read image", e))
+        .and_then(|original| {
+            image::load_from_memory(&original)
+                .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to open image: {}", e) })
+        })
+        .and_then(|img| {
+            let mut encoded = Vec::new();
+            encode_image(&mut encoded, &img, OutputFormat::WebP, DEFAULT_JPEG_QUALITY)?;
+            Ok(encoded)
+        });
+    let encoded = match encoded {
+        Ok(encoded) => encoded,
+        Err(e) => {
+            tracing::warn!(image_id = %image_id, error = %e, "webp_migration_failed");
+            file.error = Some(e);
+            return file;
+        }
+    };
+    file.new_size = Some(encoded.len() as u64);
+    // Re-encoding an already-lossy JPEG doesn't always pay off
+    if encoded.len() as u64 >= file.old_size {
+        file.status = WebpMigrationStatus::NotSmaller;
+        return file;
+    }
+    if !dry_run {
+        if let Err(e) = write_atomic(&webp_path, &encoded) {
+            fs::remove_file(&webp_path).ok();
+            file.error = Some(AppError::io("Failed to write WebP image", e));
+            return file;
+        }
+        if delete_original {
+            if let Err(e) = fs::remove_file(&jpeg_path) {
+                // The WebP is complete; a JPEG left behind is a kept copy, which reads skip
+                tracing::warn!(image_id = %image_id, error = %e, "webp_migration_delete_failed");
+            }
+        }
+    }
+    file.status = WebpMigrationStatus::Migrated;
+    file
+}
+
+/// Re-encode every stored `{id}.jpg` as a lossy `{id}.webp` at quality 75 to reclaim
+/// space (thumbnails excluded)
+/// - `delete_originals`: remove each JPEG once its WebP is written (or, for ids migrated
+///   earlier, once the existing WebP is checked to decode)
+/// - `dry_run`: encode in memory and report sizes, writing and deleting nothing
+///
+/// Without `delete_originals` each JPEG stays next to its WebP as a kept copy: listing,
+/// stats, dedup and lookups then treat the WebP as the image (see storage.rs
+/// is_superseded_jpeg). Images whose WebP wouldn't be smaller are skipped, and a JPEG
+/// that fails to decode is left in place and reported.
+/// Emits `webp-migration-progress` (same payload as compress-progress) after each file
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(trace_id = trace_id.as_deref().unwrap_or("no-trace")), err(Display))]
+pub async fn migrate_store_to_webp(
+    app: AppHandle,
+    delete_originals: bool,
+    dry_run: bool,
+    trace_id: Option<String>,
+) -> Result<WebpMigrationResult, AppError> {
+    run_blocking(move || {
+        let dir = get_data_dir()?;
+        let entries = fs::read_dir(&dir).map_err(|e| AppError::io("Failed to read images directory", e))?;
+        let mut image_ids: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let id = name.strip_suffix(".jpg")?;
+                matches!(parse_stored_image_name(&name), Some((_, false))).then(|| id.to_string())
+            })
+            .collect();
+        image_ids.sort();
+
+        let span = tracing::Span::current();
+        let total = image_ids.len();
+        let completed = AtomicUsize::new(0);
+        let files: Vec<WebpMigrationFile> = image_ids
+            .par_iter()
+            .map(|image_id| {
+                let file = span.in_scope(|| migrate_file_to_webp(&dir, image_id, delete_originals, dry_run));
+                let progress = CompressProgress {
+                    completed: completed.fetch_add(1, Ordering::SeqCst) + 1,
+                    total,
+                    current_id: image_id.clone(),
+                };
+                app.emit("webp-migration-progress", progress).ok();
+                file
+            })
+            .collect();
+
+        let saved_bytes = files
+            .iter()
+            .filter(|f| f.status == WebpMigrationStatus::Migrated)
+            .map(|f| f.old_size.saturating_sub(f.new_size.unwrap_or(0)))
+            .sum();
+        let deleted_count = if delete_originals && !dry_run {
+            files
+                .iter()
+                .filter(|f| matches!(f.status, WebpMigrationStatus::Migrated | WebpMigrationStatus::AlreadyMigrated))
+                .filter(|f| !output_path_for(&dir, &f.id, OutputFormat::Jpeg).exists())
+                .count() as u32
+        } else {
+            0
+        };
+        tracing::info!(total, saved_bytes, deleted_count, dry_run, "webp_migration_finished");
+        Ok(WebpMigrationResult { files, saved_bytes, deleted_count, dry_run })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic grayscale gradient with some texture, sized past a JPEG block boundary
+    fn fixture_image() -> DynamicImage {
+        let img = image::GrayImage::from_fn(203, 117, |x, y| image::Luma([((x * 7 + y * 13) % 256) as u8]));
+        DynamicImage::ImageLuma8(img)
+    }
+
+    #[test]
+    fn in_memory_encode_matches_direct_file_encode() {
+        let img = fixture_image();
+        for format in [OutputFormat::Jpeg, OutputFormat::WebP, OutputFormat::Png] {
+            let path = std::env::temp_dir().join(format!(
+                "yorutsuke-encode-{}-{}.{}",
+                format.name(),
+                std::process::id(),
+                format.extension()
+            ));
+            // Previous pipeline: encode straight into the output file, then read it back
+            encode_image(fs::File::create(&path).unwrap(), &img, format, DEFAULT_JPEG_QUALITY).unwrap();
+            let on_disk = fs::read(&path).unwrap();
+            fs::remove_file(&path).ok();
+
+            let mut in_memory = Vec::new();
+            encode_image(&mut in_memory, &img, format, DEFAULT_JPEG_QUALITY).unwrap();
+
+            assert_eq!(in_memory, on_disk, "{} bytes differ", format.name());
+            assert_eq!(format!("{:x}", md5::compute(&in_memory)), format!("{:x}", md5::compute(&on_disk)));
+        }
+    }
+
+    #[test]
+    fn cancelled_batch_skips_remaining_items() {
+        let batches = CompressBatches::default();
+        let options = CompressOptions::from_args(None).unwrap();
+        // Missing inputs fail fast, which is enough to drive the batch loop
+        let items: Vec<(String, String)> = (0..20)
+            .map(|i| (format!("/nonexistent/yorutsuke-batch-{}.jpg", i), format!("batch-item-{}", i)))
+            .collect();
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let (resume_tx, resume_rx) = std::sync::mpsc::channel::<()>();
+        let resume_rx = Mutex::new(resume_rx);
+
+        let result = std::thread::scope(|scope| {
+            let batch = scope.spawn(|| {
+                compress_batch(&batches, items, &options, 1, Some("batch-1".to_string()), |progress| {
+                    // Hold the single worker after the first item until the cancel is in
+                    if progress.completed == 1 {
+                        started_tx.send(()).unwrap();
+                        resume_rx.lock().unwrap().recv().unwrap();
+                    }
+                })
+            });
+            started_rx.recv().unwrap();
+            assert!(batches.cancel("batch-1"));
+            resume_tx.send(()).unwrap();
+            batch.join().unwrap().unwrap()
+        });
+
+        assert!(result.cancelled);
+        assert!(result.errors[0].is_some());
+        let skipped = result.results.iter().zip(&result.errors).filter(|(r, e)| r.is_none() && e.is_none()).count();
+        assert!(skipped > 0 && skipped < 20, "skipped {}", skipped);
+        // The registration is gone once the batch returns
+        assert!(!batches.cancel("batch-1"));
+    }
+
+    #[test]
+    fn overwrite_in_another_format_removes_the_old_output() {
+        let dir = std::env::temp_dir().join(format!("yorutsuke-overwrite-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.png");
+        fixture_image().save(&source).unwrap();
+        let options = |format| CompressOptions {
+            format: FormatSelection::Fixed(format),
+            output_dir: Some(dir.clone()),
+            ..CompressOptions::from_args(None).unwrap()
+        };
+        let source = source.to_string_lossy().to_string();
+
+        compress_file(source.clone(), "receipt".to_string(), &options(OutputFormat::Jpeg)).unwrap();
+        let result = compress_file(source, "receipt".to_string(), &options(OutputFormat::WebP)).unwrap();
+        assert_eq!(result.chosen_format, "webp");
+        assert_eq!(existing_outputs(&dir, "receipt"), vec![dir.join("receipt.webp")]);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn failed_overwrite_keeps_the_previous_image() {
+        let dir = std::env::temp_dir().join(format!("yorutsuke-overwrite-failed-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.png");
+        fixture_image().save(&source).unwrap();
+        let source = source.to_string_lossy().to_string();
+        let options = CompressOptions {
+            format: FormatSelection::Fixed(OutputFormat::Jpeg),
+            output_dir: Some(dir.clone()),
+            ..CompressOptions::from_args(None).unwrap()
+        };
+        compress_file(source.clone(), "receipt".to_string(), &options).unwrap();
+        let previous = fs::read(dir.join("receipt.jpg")).unwrap();
+
+        // Storing the original can't succeed: its destination is a directory
+        fs::create_dir(dir.join("receipt_original.png")).unwrap();
+        let failing = CompressOptions {
+            quality: 40,
+            verify_output: true,
+            keep_original: true,
+            ..options
+        };
+        assert!(compress_file(source, "receipt".to_string(), &failing).is_err());
+        assert_eq!(fs::read(dir.join("receipt.jpg")).unwrap(), previous);
+        // Only the source, the old image and the directory remain (no staged file)
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn small_color_jpeg_is_only_passed_through_for_color_output() {
+        let dir = std::env::temp_dir().join(format!("yorutsuke-passthrough-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let color = DynamicImage::ImageRgb8(image::RgbImage::from_fn(200, 100, |x, y| {
+            image::Rgb([(x % 256) as u8, (y * 2) as u8, 90])
+        }));
+        let source = dir.join("source.jpg");
+        encode_image(fs::File::create(&source).unwrap(), &color, OutputFormat::Jpeg, 90).unwrap();
+        let source = source.to_string_lossy().to_string();
+        let options = |grayscale| CompressOptions {
+            grayscale,
+            output_dir: Some(dir.clone()),
+            ..CompressOptions::from_args(None).unwrap()
+        };
+
+        let gray = compress_file(source.clone(), "gray".to_string(), &options(true)).unwrap();
+        assert!(!gray.was_passthrough && gray.grayscale);
+        assert_eq!(gray.quality_used, Some(DEFAULT_JPEG_QUALITY));
+
+        let kept = compress_file(source, "color".to_string(), &options(false)).unwrap();
+        assert!(kept.was_passthrough && !kept.grayscale);
+        assert_eq!(kept.quality_used, None);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn failed_thumbnail_rotation_leaves_the_image_as_it_was() {
+        let dir = std::env::temp_dir().join(format!("yorutsuke-rotate-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let mut jpeg = Vec::new();
+        encode_image(&mut jpeg, &fixture_image(), OutputFormat::Jpeg, DEFAULT_JPEG_QUALITY).unwrap();
+        fs::write(dir.join("receipt.jpg"), &jpeg).unwrap();
+        fs::write(dir.join("receipt_thumb.jpg"), b"not a jpeg").unwrap();
+
+        assert!(rotate_stored_in(&dir, "receipt", 90, DEFAULT_JPEG_QUALITY).is_err());
+        assert_eq!(fs::read(dir.join("receipt.jpg")).unwrap(), jpeg);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        let mut thumb = Vec::new();
+        encode_image(&mut thumb, &fixture_image().thumbnail(64, 64), OutputFormat::Jpeg, DEFAULT_JPEG_QUALITY).unwrap();
+        fs::write(dir.join("receipt_thumb.jpg"), &thumb).unwrap();
+        let result = rotate_stored_in(&dir, "receipt", 90, DEFAULT_JPEG_QUALITY).unwrap();
+        assert_eq!((result.width, result.height), (117, 203));
+        let rotated_thumb = image::open(dir.join("receipt_thumb.jpg")).unwrap();
+        assert!(rotated_thumb.height() > rotated_thumb.width());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn webp_migration_only_deletes_jpegs_backed_by_a_valid_webp() {
+        let dir = std::env::temp_dir().join(format!("yorutsuke-webp-migration-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        // A photo-like JPEG at high quality, which lossy WebP at 75 beats
+        let photo = DynamicImage::ImageLuma8(image::GrayImage::from_fn(400, 300, |x, y| {
+            let noise = ((x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 7) % 17;
+            image::Luma([((x / 3 + y / 2) % 200) as u8 + noise as u8])
+        }));
+        let mut jpeg = Vec::new();
+        encode_image(&mut jpeg, &photo, OutputFormat::Jpeg, 95).unwrap();
+        for id in ["kept", "fresh", "garbage"] {
+            fs::write(dir.join(format!("{}.jpg", id)), &jpeg).unwrap();
+        }
+        // Truncated WebP from an interrupted earlier write
+        fs::write(dir.join("garbage.webp"), b"webp").unwrap();
+
+        let dry = migrate_file_to_webp(&dir, "fresh", true, true);
+        assert!(dry.status == WebpMigrationStatus::Migrated);
+        assert!(dir.join("fresh.jpg").exists() && !dir.join("fresh.webp").exists());
+
+        let kept = migrate_file_to_webp(&dir, "kept", false, false);
+        assert!(kept.status == WebpMigrationStatus::Migrated);
+        assert!(dir.join("kept.jpg").exists() && dir.join("kept.webp").exists());
+        // A later run with delete_originals removes the kept JPEG once the WebP checks out
+        let again = migrate_file_to_webp(&dir, "kept", true, false);
+        assert!(again.status == WebpMigrationStatus::AlreadyMigrated);
+        assert!(!dir.join("kept.jpg").exists());
+
+        let fresh = migrate_file_to_webp(&dir, "fresh", true, false);
+        assert!(fresh.status == WebpMigrationStatus::Migrated);
+        assert!(fresh.new_size.unwrap() < fresh.old_size);
+        assert!(!dir.join("fresh.jpg").exists() && dir.join("fresh.webp").exists());
+
+        let garbage = migrate_file_to_webp(&dir, "garbage", true, false);
+        assert!(garbage.status == WebpMigrationStatus::Failed && garbage.error.is_some());
+        assert_eq!(fs::read(dir.join("garbage.jpg")).unwrap(), jpeg);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn single_pass_hash_matches_two_pass_hash() {
+        let img = fixture_image();
+        for format in [OutputFormat::Jpeg, OutputFormat::WebP, OutputFormat::Png] {
+            let path = std::env::temp_dir().join(format!(
+                "yorutsuke-hashed-{}-{}.{}",
+                format.name(),
+                std::process::id(),
+                format.extension()
+            ));
+            let (md5, size) = encode_to_file(&path, &img, format, DEFAULT_JPEG_QUALITY, false).unwrap();
+            // Previous pipeline: write the file, then hash it in a second pass
+            let two_pass = hash_file(&path, HashAlgorithm::Md5).unwrap();
+            let on_disk_size = fs::metadata(&path).unwrap().len();
+            fs::remove_file(&path).ok();
+
+            assert_eq!(md5, two_pass, "{} hash differs", format.name());
+            assert_eq!(size, on_disk_size);
+            let (in_memory, memory_md5) = encode_hashed(&img, format, DEFAULT_JPEG_QUALITY).unwrap();
+            assert_eq!(memory_md5, format!("{:x}", md5::compute(&in_memory)));
+            assert_eq!(memory_md5, two_pass);
+        }
+    }
+
+    #[test]
+    fn sixteen_gray_levels_encode_smaller_than_full_depth() {
+        // Receipt-like: dark text rows on paper with deterministic sensor-style noise
+        let full = image::GrayImage::from_fn(512, 384, |x, y| {
+            let noise = ((x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 7) % 9;
+            let ink = y % 24 < 10 && x % 12 < 7;
+            image::Luma([if ink { 20 + noise as u8 } else { 200 + noise as u8 }])
+        });
+        let mut posterized = full.clone();
+        posterize(&mut posterized, 16);
+
+        let encode = |img: image::GrayImage| {
+            let mut out = Vec::new();
+            encode_image(&mut out, &DynamicImage::ImageLuma8(img), OutputFormat::Jpeg, DEFAULT_JPEG_QUALITY).unwrap();
+            out.len()
+        };
+        let (full_size, posterized_size) = (encode(full), encode(posterized));
+        assert!(posterized_size < full_size, "16 levels: {} bytes, full: {} bytes", posterized_size, full_size);
+    }
+
+    #[test]
+    fn enhance_stretches_only_low_contrast_images() {
+        // Faded thermal print: gray text (150) on light gray paper (210)
+        let faded = image::GrayImage::from_fn(200, 100, |x, y| {
+            image::Luma([if y % 20 < 8 && x % 10 < 6 { 150 } else { 210 }])
+        });
+        let mut stretched = faded.clone();
+        assert!(enhance_contrast(&mut stretched));
+        let (low, high) = histogram_percentiles(&stretched, 0.0, 1.0);
+        assert_eq!((low, high), (0, 255));
+
+        let crisp = image::GrayImage::from_fn(200, 100, |x, y| {
+            image::Luma([if y % 20 < 8 && x % 10 < 6 { 20 } else { 235 }])
+        });
+        let mut untouched = crisp.clone();
+        assert!(!enhance_contrast(&mut untouched));
+        assert_eq!(untouched, crisp);
+    }
+
+    #[test]
+    fn posterize_keeps_black_and_white() {
+        let mut img = image::GrayImage::from_fn(256, 1, |x, _| image::Luma([x as u8]));
+        posterize(&mut img, 4);
+        let mut values: Vec<u8> = img.pixels().map(|p| p.0[0]).collect();
+        values.dedup();
+        assert_eq!(values, vec![0, 85, 170, 255]);
+    }
+}
@@ -0,0 +1,277 @@
+//! Persisted app settings (~/.yorutsuke/config.json)
+//! The single source of truth for settings owned by the Rust side; the file carries a
+//! `schema_version` and older files are migrated on load
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::crypto::parse_salt;
+use crate::error::AppError;
+use crate::logging::{LogDateBasis, LOG_RATE_LIMIT_RANGE, LOG_RETENTION_RANGE};
+use crate::storage::{app_home_dir, write_atomic};
+
+/// Current config.json layout; bump it and add a step to `migrate` when fields change
+pub const CONFIG_SCHEMA_VERSION: u32 = 1;
+
+/// Settings stored in config.json; missing fields take their defaults
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Layout version of the file (always CONFIG_SCHEMA_VERSION once loaded)
+    pub schema_version: u32,
+    /// Directory holding `images/`; None means the platform default
+    pub storage_root: Option<String>,
+    /// Write new images as AES-GCM encrypted `{id}.enc` files (see crypto.rs)
+    pub encrypt_images: bool,
+    /// Hex Argon2 salt for encrypted storage, created on first use (see crypto.rs)
+    pub encryption_salt: Option<String>,
+    /// Days of logs log_cleanup keeps by default; None means 7
+    pub log_retention_days: Option<u32>,
+    /// Most frontend log entries written per second; None means 500
+    pub log_rate_limit: Option<u32>,
+    /// Clock log files are dated by ("local" or "utc"); applies from the next launch
+    pub log_date_basis: LogDateBasis,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            schema_version: CONFIG_SCHEMA_VERSION,
+            storage_root: None,
+            encrypt_images: false,
+            encryption_salt: None,
+            log_retention_days: None,
+            log_rate_limit: None,
+            log_date_basis: LogDateBasis::Local,
+        }
+    }
+}
+
+impl Config {
+    /// Check values serde can't: ranges and path shapes
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(root) = &self.storage_root {
+            if !Path::new(root).is_absolute() {
+                return Err(AppError::ConfigInvalid {
+                    reason: format!("storage_root must be an absolute path: {}", root),
+                });
+            }
+        }
+        if let Some(salt) = &self.encryption_salt {
+            if parse_salt(salt).is_none() {
+                return Err(AppError::ConfigInvalid {
+                    reason: format!("encryption_salt must be 32 hex digits: {}", salt),
+                });
+            }
+        }
+        if let Some(days) = self.log_retention_days {
+            if !LOG_RETENTION_RANGE.contains(&days) {
+                return Err(AppError::ConfigInvalid {
+                    reason: format!("log_retention_days out of range: {} (expected 1-365)", days),
+                });
+            }
+        }
+        if let Some(limit) = self.log_rate_limit {
+            if !LOG_RATE_LIMIT_RANGE.contains(&limit) {
+                return Err(AppError::ConfigInvalid {
+                    reason: format!("log_rate_limit out of range: {} (expected 10-100000)", limit),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Path of config.json; only write_config creates its folder, so reads have no side effects
+pub fn config_path() -> PathBuf {
+    app_home_dir().join("config.json")
+}
+
+/// Upgrade a parsed config file from `from` to CONFIG_SCHEMA_VERSION in place
+/// Each step rewrites the JSON for one version bump, so old files replay every step
+fn migrate(value: &mut Value, from: u32) -> Result<(), AppError> {
+    let Some(object) = value.as_object_mut() else {
+        return Err(AppError::ConfigInvalid { reason: "expected a JSON object".to_string() });
+    };
+    for version in from..CONFIG_SCHEMA_VERSION {
+        match version {
+            // v0 (no schema_version) has the same fields as v1
+            0 => {}
+            _ => unreachable!("no migration from config v{}", version),
+        }
+    }
+    object.insert("schema_version".to_string(), Value::from(CONFIG_SCHEMA_VERSION));
+    Ok(())
+}
+
+/// Parse config.json contents: migrate, apply defaults, then type-check and validate
+pub fn parse_config(json: &[u8]) -> Result<Config, AppError> {
+    let mut value: Value = serde_json::from_slice(json)
+        .map_err(|e| AppError::ConfigInvalid { reason: format!("not valid JSON: {}", e) })?;
+    let version = match value.get("schema_version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| AppError::ConfigInvalid { reason: format!("invalid schema_version: {}", v) })?,
+    };
+    if version > CONFIG_SCHEMA_VERSION {
+        return Err(AppError::ConfigInvalid {
+            reason: format!(
+                "schema_version {} is newer than this app supports ({})",
+                version, CONFIG_SCHEMA_VERSION
+            ),
+        });
+    }
+    migrate(&mut value, version)?;
+
+    let config: Config = serde_json::from_value(value)
+        .map_err(|e| AppError::ConfigInvalid { reason: e.to_string() })?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Load the config, falling back to defaults when the file is missing or invalid
+/// Storage must keep working even if the file is damaged, so errors are only logged
+/// here; load_config surfaces them
+pub fn read_config() -> Config {
+    let path = config_path();
+    let Ok(json) = fs::read(&path) else { return Config::default() };
+    parse_config(&json).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "config_invalid_using_defaults");
+        Config::default()
+    })
+}
+
+/// Save the config atomically (a crash mid-save keeps the previous file)
+pub fn write_config(config: &Config) -> Result<(), AppError> {
+    // ~/.yorutsuke may not exist yet on a fresh install
+    fs::create_dir_all(app_home_dir()).map_err(|e| AppError::io("Failed to create app folder", e))?;
+    write_config_to(&config_path(), config)
+}
+
+fn write_config_to(path: &Path, config: &Config) -> Result<(), AppError> {
+    let config = Config { schema_version: CONFIG_SCHEMA_VERSION, ..config.clone() };
+    let json = serde_json::to_vec_pretty(&config)
+        .map_err(|e| AppError::InvalidArgument { reason: format!("Failed to serialize config: {}", e) })?;
+    write_atomic(path, &json).map_err(|e| AppError::io("Failed to save config", e))
+}
+
+/// Load config.json (defaults when it doesn't exist yet)
+/// Unlike the internal reader, a damaged or mistyped file is a CONFIG_INVALID error
+/// so Settings can offer to reset it
+#[tauri::command]
+pub fn load_config() -> Result<Config, AppError> {
+    load_config_from(&config_path())
+}
+
+fn load_config_from(path: &Path) -> Result<Config, AppError> {
+    match fs::read(path) {
+        Ok(json) => parse_config(&json),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(AppError::io("Failed to read config", e)),
+    }
+}
+
+/// Change some settings and save, keeping every other field
+/// Starts from load_config, so a damaged (or newer) file fails with CONFIG_INVALID and is
+/// left alone instead of being replaced by defaults (losing e.g. storage_root or the
+/// encryption salt); every setter goes through this
+pub fn update_config(update: impl FnOnce(&mut Config)) -> Result<(), AppError> {
+    fs::create_dir_all(app_home_dir()).map_err(|e| AppError::io("Failed to create app folder", e))?;
+    update_config_at(&config_path(), update)
+}
+
+fn update_config_at(path: &Path, update: impl FnOnce(&mut Config)) -> Result<(), AppError> {
+    let mut config = load_config_from(path)?;
+    update(&mut config);
+    write_config_to(path, &config)
+}
+
+/// Validate and save the whole config (replaces every field)
+/// Note that a changed `storage_root` is not migrated; use migrate_storage for that
+#[tauri::command]
+pub fn save_config(config: Config) -> Result<(), AppError> {
+    config.validate()?;
+    write_config(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let config = parse_config(br#"{"encrypt_images": true}"#).unwrap();
+        assert_eq!(config, Config { encrypt_images: true, ..Config::default() });
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let config = Config {
+            storage_root: Some(if cfg!(windows) { r"D:\Receipts" } else { "/mnt/receipts" }.to_string()),
+            encrypt_images: true,
+            log_retention_days: Some(30),
+            log_date_basis: LogDateBasis::Utc,
+            ..Config::default()
+        };
+        let json = serde_json::to_vec(&config).unwrap();
+        assert_eq!(parse_config(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn unversioned_file_is_migrated_to_current_version() {
+        let config = parse_config(br#"{"log_retention_days": 14}"#).unwrap();
+        assert_eq!(config.schema_version, CONFIG_SCHEMA_VERSION);
+        assert_eq!(config.log_retention_days, Some(14));
+    }
+
+    #[test]
+    fn wrong_field_type_is_rejected() {
+        let err = parse_config(br#"{"encrypt_images": "yes"}"#).unwrap_err();
+        assert_eq!(err.code(), "CONFIG_INVALID");
+    }
+
+    #[test]
+    fn out_of_range_value_is_rejected() {
+        let err = parse_config(br#"{"log_retention_days": 0}"#).unwrap_err();
+        assert_eq!(err.code(), "CONFIG_INVALID");
+    }
+
+    #[test]
+    fn malformed_encryption_salt_is_rejected() {
+        let salt = "00112233445566778899aabbccddeeff";
+        let config = parse_config(format!(r#"{{"encryption_salt": "{}"}}"#, salt).as_bytes()).unwrap();
+        assert_eq!(config.encryption_salt.as_deref(), Some(salt));
+        let err = parse_config(br#"{"encryption_salt": "0011zz"}"#).unwrap_err();
+        assert_eq!(err.code(), "CONFIG_INVALID");
+    }
+
+    #[test]
+    fn update_leaves_a_damaged_file_alone() {
+        let path = std::env::temp_dir().join(format!("yorutsuke-config-update-{}.json", std::process::id()));
+        let newer = format!(r#"{{"schema_version": {}, "storage_root": "/mnt/receipts"}}"#, CONFIG_SCHEMA_VERSION + 1);
+        for damaged in [br#"{"storage_root": "/mnt/receipts", "#.to_vec(), newer.into_bytes()] {
+            fs::write(&path, &damaged).unwrap();
+            let err = update_config_at(&path, |config| config.encrypt_images = true).unwrap_err();
+            assert_eq!(err.code(), "CONFIG_INVALID");
+            assert_eq!(fs::read(&path).unwrap(), damaged);
+        }
+
+        fs::write(&path, br#"{"storage_root": "/mnt/receipts"}"#).unwrap();
+        update_config_at(&path, |config| config.encrypt_images = true).unwrap();
+        let config = load_config_from(&path).unwrap();
+        assert!(config.encrypt_images);
+        assert_eq!(config.storage_root.as_deref(), Some("/mnt/receipts"));
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn newer_schema_version_is_rejected() {
+        let json = format!(r#"{{"schema_version": {}}}"#, CONFIG_SCHEMA_VERSION + 1);
+        let err = parse_config(json.as_bytes()).unwrap_err();
+        assert_eq!(err.code(), "CONFIG_INVALID");
+    }
+}
@@ -0,0 +1,109 @@
+//! Contact sheet: one JPEG tiling thumbnails of several stored receipts (visual history)
+
+use std::fs;
+use std::io::BufWriter;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{imageops, DynamicImage, Rgb, RgbImage};
+use rayon::prelude::*;
+
+use crate::error::AppError;
+use crate::storage::find_stored_image;
+use crate::task::run_blocking;
+
+/// Tile edge in pixels; thumbnails are fitted inside and centered
+const TILE_SIZE: u32 = 240;
+
+/// Gap between tiles and around the sheet
+const TILE_GAP: u32 = 8;
+
+/// Most tiles on one sheet (10 x 10 at 240px is already ~2.5k px square)
+const MAX_TILES: usize = 100;
+
+/// Allowed `columns`
+const MAX_COLUMNS: u32 = 20;
+
+const CONTACT_SHEET_QUALITY: u8 = 85;
+
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const PLACEHOLDER: Rgb<u8> = Rgb([200, 200, 200]);
+
+/// A written contact sheet
+#[derive(serde::Serialize)]
+pub struct ContactSheet {
+    /// JPEG in the temp directory
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    /// Tiles drawn (at most 100; later ids are dropped)
+    pub tile_count: u32,
+    /// Ids drawn as gray placeholders (missing, encrypted or undecodable)
+    pub missing_ids: Vec<String>,
+}
+
+/// Thumbnail for one stored image, or None when it can't be shown
+fn load_tile(image_id: &str) -> Result<Option<RgbImage>, AppError> {
+    let Some(path) = find_stored_image(image_id)? else { return Ok(None) };
+    Ok(image::open(&path).ok().map(|img| img.thumbnail(TILE_SIZE, TILE_SIZE).to_rgb8()))
+}
+
+/// Tile thumbnails of `image_ids` (in order) into a grid `columns` wide on white
+/// Images that are missing or fail to decode get a gray placeholder tile; invalid ids
+/// fail the whole call. Only the first 100 ids are used
+#[tauri::command]
+pub async fn generate_contact_sheet(image_ids: Vec<String>, columns: u32) -> Result<ContactSheet, AppError> {
+    if !(1..=MAX_COLUMNS).contains(&columns) {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid columns: {} (expected 1-{})", columns, MAX_COLUMNS),
+        });
+    }
+    if image_ids.is_empty() {
+        return Err(AppError::InvalidArgument { reason: "No images for contact sheet".to_string() });
+    }
+
+    run_blocking(move || {
+        let ids = &image_ids[..image_ids.len().min(MAX_TILES)];
+        let tiles = ids.par_iter().map(|id| load_tile(id)).collect::<Result<Vec<_>, _>>()?;
+
+        let count = tiles.len() as u32;
+        let columns = columns.min(count);
+        let rows = count.div_ceil(columns);
+        let width = columns * (TILE_SIZE + TILE_GAP) + TILE_GAP;
+        let height = rows * (TILE_SIZE + TILE_GAP) + TILE_GAP;
+        let mut sheet = RgbImage::from_pixel(width, height, BACKGROUND);
+
+        let mut missing_ids = Vec::new();
+        for (index, (tile, id)) in tiles.iter().zip(ids).enumerate() {
+            let index = index as u32;
+            let x = TILE_GAP + (index % columns) * (TILE_SIZE + TILE_GAP);
+            let y = TILE_GAP + (index / columns) * (TILE_SIZE + TILE_GAP);
+            match tile {
+                Some(thumb) => {
+                    let dx = (TILE_SIZE - thumb.width()) / 2;
+                    let dy = (TILE_SIZE - thumb.height()) / 2;
+                    imageops::overlay(&mut sheet, thumb, i64::from(x + dx), i64::from(y + dy));
+                }
+                None => {
+                    let placeholder = RgbImage::from_pixel(TILE_SIZE, TILE_SIZE, PLACEHOLDER);
+                    imageops::replace(&mut sheet, &placeholder, i64::from(x), i64::from(y));
+                    missing_ids.push(id.clone());
+                }
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("yorutsuke-contact-sheet-{}.jpg", uuid::Uuid::new_v4()));
+        let file = fs::File::create(&path).map_err(|e| AppError::io_at(&path, "Failed to create contact sheet", e))?;
+        JpegEncoder::new_with_quality(BufWriter::new(file), CONTACT_SHEET_QUALITY)
+            .encode_image(&DynamicImage::ImageRgb8(sheet))
+            .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to encode contact sheet: {}", e) })?;
+
+        Ok(ContactSheet {
+            path: path.to_string_lossy().to_string(),
+            width,
+            height,
+            tile_count: count,
+            missing_ids,
+        })
+    })
+    .await
+}
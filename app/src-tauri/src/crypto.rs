@@ -0,0 +1,150 @@
+//! Optional encryption at rest for stored images (AES-256-GCM)
+//! For shared machines: with `encrypt_images` on in config.json, compress_image writes
+//! `{id}.enc` instead of a plaintext image. The key is derived with Argon2 from the
+//! user's passphrase and a random salt generated once and kept in config.json; every
+//! file also records its salt, so it opens with the passphrase alone even if the config
+//! is reset or the images directory is moved to another machine
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use sha2::{Digest, Sha256};
+
+use crate::config::{load_config, read_config, update_config, write_config};
+use crate::device::get_machine_id;
+use crate::error::AppError;
+
+/// Extension of encrypted stored images
+pub const ENCRYPTED_EXTENSION: &str = "enc";
+
+/// File header: magic, format version, the Argon2 salt (v2), then the per-file nonce
+/// v1 files have no salt field; their key was salted with the machine id
+const MAGIC: &[u8; 4] = b"YRTE";
+const FORMAT_VERSION: u8 = 2;
+const LEGACY_FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+const LEGACY_HEADER_LEN: usize = MAGIC.len() + 1 + NONCE_LEN;
+
+/// AES-256 key derived from the passphrase; derive once per command, not per image
+/// Keeps the passphrase so files written under another salt can still be opened
+pub struct StorageKey {
+    passphrase: String,
+    salt: [u8; SALT_LEN],
+    key: [u8; 32],
+}
+
+/// Whether new images should be written encrypted (config `encrypt_images`, off by default)
+pub fn encryption_enabled() -> bool {
+    read_config().encrypt_images
+}
+
+/// The install's salt from config.json, generated and saved on first use
+/// A damaged config.json is not overwritten: the salt is then used for this command only,
+/// which still works since every file carries its salt
+fn storage_salt() -> Result<[u8; SALT_LEN], AppError> {
+    let config = load_config();
+    if let Some(salt) = config.as_ref().ok().and_then(|c| c.encryption_salt.as_deref()).and_then(parse_salt) {
+        return Ok(salt);
+    }
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    match config {
+        Ok(mut config) => {
+            config.encryption_salt = Some(salt.iter().map(|b| format!("{:02x}", b)).collect());
+            write_config(&config)?;
+            tracing::info!("encryption_salt_created");
+        }
+        Err(e) => tracing::warn!(error = %e, "encryption_salt_not_saved"),
+    }
+    Ok(salt)
+}
+
+/// Parse the hex salt stored in config.json
+pub fn parse_salt(hex: &str) -> Option<[u8; SALT_LEN]> {
+    if hex.len() != SALT_LEN * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let mut salt = [0u8; SALT_LEN];
+    for (byte, pair) in salt.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(salt)
+}
+
+/// Argon2 key for `passphrase` under `salt`
+fn argon2_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], AppError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| AppError::InvalidArgument { reason: format!("Failed to derive key: {}", e) })?;
+    Ok(key)
+}
+
+/// Derive the storage key from `passphrase` and the install's salt
+/// Argon2 is deliberately slow (tens of ms), so batch commands derive the key once
+pub fn derive_key(passphrase: &str) -> Result<StorageKey, AppError> {
+    if passphrase.is_empty() {
+        return Err(AppError::InvalidArgument { reason: "Passphrase must not be empty".to_string() });
+    }
+    let salt = storage_salt()?;
+    let key = argon2_key(passphrase, &salt)?;
+    Ok(StorageKey { passphrase: passphrase.to_string(), salt, key })
+}
+
+/// Encrypt `plaintext` with a fresh random nonce, returning header + ciphertext
+pub fn encrypt(key: &StorageKey, plaintext: &[u8]) -> Result<Vec<u8>, AppError> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to encrypt image: {}", e) })?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    out.extend_from_slice(&key.salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Decrypt a file written by `encrypt`
+/// The key follows the salt in the file's header (re-derived when it isn't the install's
+/// current salt); v1 files fall back to the machine-id salt they were written with
+/// A wrong passphrase and a tampered file are indistinguishable (both fail the GCM tag)
+pub fn decrypt(key: &StorageKey, data: &[u8]) -> Result<Vec<u8>, AppError> {
+    if data.len() < LEGACY_HEADER_LEN || !data.starts_with(MAGIC) {
+        return Err(AppError::DecodeFailed { reason: "Not an encrypted image file".to_string() });
+    }
+    let version = data[MAGIC.len()];
+    let (file_key, nonce, ciphertext) = match version {
+        FORMAT_VERSION if data.len() >= HEADER_LEN => {
+            let salt = &data[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+            let file_key = if salt == key.salt { key.key } else { argon2_key(&key.passphrase, salt)? };
+            (file_key, &data[HEADER_LEN - NONCE_LEN..HEADER_LEN], &data[HEADER_LEN..])
+        }
+        FORMAT_VERSION => {
+            return Err(AppError::DecodeFailed { reason: "Not an encrypted image file".to_string() });
+        }
+        LEGACY_FORMAT_VERSION => {
+            let salt = Sha256::digest(get_machine_id()?.id.as_bytes());
+            let file_key = argon2_key(&key.passphrase, &salt)?;
+            (file_key, &data[MAGIC.len() + 1..LEGACY_HEADER_LEN], &data[LEGACY_HEADER_LEN..])
+        }
+        _ => return Err(AppError::UnsupportedFormat { format: format!("encrypted image v{}", version) }),
+    };
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&file_key));
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| AppError::DecryptFailed {
+        reason: "Failed to decrypt image (wrong passphrase or corrupted file)".to_string(),
+    })
+}
+
+/// Turn encrypted storage on or off for images compressed from now on
+/// Existing files are left as they are; the passphrase is never stored
+#[tauri::command]
+pub fn set_image_encryption(enabled: bool) -> Result<(), AppError> {
+    update_config(|config| config.encrypt_images = enabled)
+}
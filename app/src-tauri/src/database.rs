@@ -0,0 +1,302 @@
+//! Checks, backups and maintenance of the SQLite database the frontend owns (via
+//! tauri-plugin-sql), using the same driver as the plugin
+//! Nothing here creates or migrates the file; only maintain_database opens it for writing
+
+use std::path::{Path, PathBuf};
+
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection, SqliteConnection};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::storage::{get_app_home_dir, resolve_output_dir};
+
+/// Database the frontend opens as `sqlite:yorutsuke.db` (resolved by the SQL plugin
+/// against the app config directory)
+pub const DATABASE_FILE: &str = "yorutsuke.db";
+
+/// Tables created by the first migration (00_kernel/storage/migrations.ts); a database
+/// missing any of them was never migrated successfully
+const EXPECTED_TABLES: &[&str] =
+    &["settings", "images", "transactions", "transactions_cache", "morning_report_cache", "analytics"];
+
+/// Backups are named `yorutsuke-YYYYMMDD-HHMMSS.db`
+const BACKUP_PREFIX: &str = "yorutsuke-";
+const BACKUP_EXTENSION: &str = "db";
+
+/// Path of the app database (the file may not exist yet)
+pub fn database_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(DATABASE_FILE))
+        .map_err(|e| AppError::IoError { reason: format!("Failed to resolve app config directory: {}", e) })
+}
+
+/// `db_path` when given, else the app database
+fn resolve_database_path(app: &AppHandle, db_path: Option<String>) -> Result<PathBuf, AppError> {
+    match db_path {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => database_path(app),
+    }
+}
+
+/// Open `path` read-only (fails rather than creating a missing file)
+async fn open_read_only(path: &Path) -> Result<SqliteConnection, AppError> {
+    SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(true)
+        .connect()
+        .await
+        .map_err(|e| AppError::IoError { reason: format!("Failed to open database: {}", e) })
+}
+
+/// How long maintenance waits for the app's own connection to release its lock
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Open an existing `path` for writing; SQLite's file locks keep this safe alongside the
+/// plugin's connection, and busy_timeout makes it wait for a running write to finish
+async fn open_read_write(path: &Path) -> Result<SqliteConnection, AppError> {
+    SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(false)
+        .busy_timeout(BUSY_TIMEOUT)
+        .connect()
+        .await
+        .map_err(|e| AppError::IoError { reason: format!("Failed to open database: {}", e) })
+}
+
+fn query_failed(e: sqlx::Error) -> AppError {
+    AppError::IoError { reason: format!("Database query failed: {}", e) }
+}
+
+/// Result of check_database
+#[derive(serde::Serialize)]
+pub struct DatabaseCheck {
+    pub exists: bool,
+    pub size_bytes: u64,
+    pub page_count: u64,
+    /// `PRAGMA integrity_check` returned "ok" (false for missing or empty files)
+    pub integrity_ok: bool,
+    /// Problems reported by integrity_check (empty when ok)
+    pub integrity_errors: Vec<String>,
+    /// Expected tables that are present
+    pub tables_found: Vec<String>,
+    pub missing_tables: Vec<String>,
+}
+
+impl DatabaseCheck {
+    fn missing(exists: bool, size_bytes: u64) -> Self {
+        DatabaseCheck {
+            exists,
+            size_bytes,
+            page_count: 0,
+            integrity_ok: false,
+            integrity_errors: Vec::new(),
+            tables_found: Vec::new(),
+            missing_tables: EXPECTED_TABLES.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
+
+/// Health check of the SQLite file, e.g. at startup to fail fast on a broken database
+/// Opens it read-only, runs `PRAGMA integrity_check` and looks for the tables the
+/// migrations create. A 0-byte file (what a failed SQL init leaves behind) reports
+/// `integrity_ok: false` and every table missing instead of looking like an empty,
+/// healthy database. `db_path` defaults to the app database
+#[tauri::command]
+pub async fn check_database(app: AppHandle, db_path: Option<String>) -> Result<DatabaseCheck, AppError> {
+    let path = resolve_database_path(&app, db_path)?;
+    let size_bytes = match std::fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(DatabaseCheck::missing(false, 0)),
+        Err(e) => return Err(AppError::io_at(&path, "Failed to read database file", e)),
+    };
+    if size_bytes == 0 {
+        tracing::warn!(path = %path.display(), "database_empty_file");
+        return Ok(DatabaseCheck::missing(true, 0));
+    }
+
+    let mut conn = open_read_only(&path).await?;
+    let integrity: Vec<String> =
+        sqlx::query_scalar("PRAGMA integrity_check").fetch_all(&mut conn).await.map_err(query_failed)?;
+    let integrity_ok = integrity.len() == 1 && integrity[0] == "ok";
+    let page_count: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(&mut conn).await.map_err(query_failed)?;
+    let tables: Vec<String> = sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table'")
+        .fetch_all(&mut conn)
+        .await
+        .map_err(query_failed)?;
+    conn.close().await.ok();
+
+    let (tables_found, missing_tables): (Vec<String>, Vec<String>) = EXPECTED_TABLES
+        .iter()
+        .map(|t| t.to_string())
+        .partition(|t| tables.contains(t));
+    let check = DatabaseCheck {
+        exists: true,
+        size_bytes,
+        page_count: page_count.max(0) as u64,
+        integrity_ok,
+        integrity_errors: if integrity_ok { Vec::new() } else { integrity },
+        tables_found,
+        missing_tables,
+    };
+    if !check.integrity_ok || !check.missing_tables.is_empty() {
+        tracing::warn!(
+            integrity_ok = check.integrity_ok,
+            missing_tables = ?check.missing_tables,
+            "database_check_failed"
+        );
+    }
+    Ok(check)
+}
+
+/// Default backup directory (~/.yorutsuke/backups/)
+fn backups_dir() -> PathBuf {
+    get_app_home_dir().join("backups")
+}
+
+/// A database backup file
+#[derive(serde::Serialize)]
+pub struct DatabaseBackup {
+    pub path: String,
+    pub size_bytes: u64,
+    /// When the snapshot was taken (RFC 3339, from the file's modification time)
+    pub created_at: String,
+}
+
+impl DatabaseBackup {
+    fn from_path(path: &Path) -> Result<Self, AppError> {
+        let metadata = std::fs::metadata(path).map_err(|e| AppError::io_at(path, "Failed to read backup", e))?;
+        let created_at = metadata
+            .modified()
+            .map(|time| chrono::DateTime::<chrono::Local>::from(time).to_rfc3339())
+            .unwrap_or_default();
+        Ok(DatabaseBackup { path: path.to_string_lossy().to_string(), size_bytes: metadata.len(), created_at })
+    }
+}
+
+/// Snapshot the database to `{dest}/yorutsuke-YYYYMMDD-HHMMSS.db` before risky work
+/// (migrations, sync). Uses `VACUUM INTO`, which copies inside one read transaction, so
+/// like SQLite's online backup API the copy is consistent even while the app has the
+/// database open and writing; a plain file copy could capture a torn write.
+/// The snapshot is written under a temporary name and renamed when complete.
+/// `db_path` defaults to the app database; `dest` (a directory under home, created if
+/// missing) defaults to ~/.yorutsuke/backups
+#[tauri::command]
+pub async fn backup_database(
+    app: AppHandle,
+    db_path: Option<String>,
+    dest: Option<String>,
+) -> Result<DatabaseBackup, AppError> {
+    let path = resolve_database_path(&app, db_path)?;
+    if !path.exists() {
+        return Err(AppError::FileNotFound { path: path.to_string_lossy().to_string() });
+    }
+    let dest_dir = match dest {
+        Some(dir) => resolve_output_dir(&dir)?,
+        None => {
+            let dir = backups_dir();
+            std::fs::create_dir_all(&dir).map_err(|e| AppError::io_at(&dir, "Failed to create backup directory", e))?;
+            dir
+        }
+    };
+    let name = format!("{}{}", BACKUP_PREFIX, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let backup_path = dest_dir.join(format!("{}.{}", name, BACKUP_EXTENSION));
+    if backup_path.exists() {
+        return Err(AppError::AlreadyExists { path: backup_path.to_string_lossy().to_string() });
+    }
+    let partial_path = dest_dir.join(format!("{}.partial", name));
+    std::fs::remove_file(&partial_path).ok();
+
+    let mut conn = open_read_only(&path).await?;
+    let result = sqlx::query("VACUUM INTO ?")
+        .bind(partial_path.to_string_lossy().to_string())
+        .execute(&mut conn)
+        .await;
+    conn.close().await.ok();
+    if let Err(e) = result {
+        std::fs::remove_file(&partial_path).ok();
+        return Err(AppError::IoError { reason: format!("Failed to back up database: {}", e) });
+    }
+    std::fs::rename(&partial_path, &backup_path).map_err(|e| {
+        std::fs::remove_file(&partial_path).ok();
+        AppError::io_at(&backup_path, "Failed to save backup", e)
+    })?;
+
+    let backup = DatabaseBackup::from_path(&backup_path)?;
+    tracing::info!(path = %backup.path, size_bytes = backup.size_bytes, "database_backup_created");
+    Ok(backup)
+}
+
+/// Backups in ~/.yorutsuke/backups, newest first (for the restore UI)
+#[tauri::command]
+pub fn list_database_backups() -> Result<Vec<DatabaseBackup>, AppError> {
+    let dir = backups_dir();
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::io_at(&dir, "Failed to read backup directory", e)),
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == BACKUP_EXTENSION)
+                && path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(BACKUP_PREFIX))
+        })
+        .collect();
+    // The timestamp in the name sorts chronologically
+    paths.sort_by(|a, b| b.cmp(a));
+    paths.iter().map(|path| DatabaseBackup::from_path(path)).collect()
+}
+
+/// Database file plus its `-wal` file, in bytes (missing files count as 0)
+fn database_footprint(path: &Path) -> u64 {
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    [path.to_path_buf(), PathBuf::from(wal)]
+        .iter()
+        .filter_map(|file| std::fs::metadata(file).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Result of maintain_database; sizes include the `-wal` file
+#[derive(serde::Serialize)]
+pub struct DatabaseMaintenance {
+    pub before_bytes: u64,
+    pub after_bytes: u64,
+    /// `before_bytes - after_bytes` (0 if the database grew meanwhile)
+    pub reclaimed_bytes: u64,
+}
+
+/// Shrink the database: `PRAGMA wal_checkpoint(TRUNCATE)` folds the `-wal` file into the
+/// database and empties it, then `VACUUM` drops free pages. Safe to run while the app
+/// has the database open (SQLite's locking serializes it with the app's writes), e.g. on
+/// idle; when a write holds the lock for more than 5s it fails and can be retried later.
+/// `db_path` defaults to the app database
+#[tauri::command]
+pub async fn maintain_database(app: AppHandle, db_path: Option<String>) -> Result<DatabaseMaintenance, AppError> {
+    let path = resolve_database_path(&app, db_path)?;
+    if !path.exists() {
+        return Err(AppError::FileNotFound { path: path.to_string_lossy().to_string() });
+    }
+    let before_bytes = database_footprint(&path);
+
+    let mut conn = open_read_write(&path).await?;
+    let result = async {
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&mut conn).await?;
+        sqlx::query("VACUUM").execute(&mut conn).await?;
+        // VACUUM in WAL mode writes through the -wal file, so fold that back in too
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&mut conn).await
+    }
+    .await;
+    conn.close().await.ok();
+    result.map_err(|e| AppError::IoError { reason: format!("Database maintenance failed: {}", e) })?;
+
+    let after_bytes = database_footprint(&path);
+    let maintenance =
+        DatabaseMaintenance { before_bytes, after_bytes, reclaimed_bytes: before_bytes.saturating_sub(after_bytes) };
+    tracing::info!(before_bytes, after_bytes, "database_maintained");
+    Ok(maintenance)
+}
@@ -0,0 +1,110 @@
+//! Developer and support utilities, only built with the `dev-tools` feature
+//! (`cargo tauri dev --features dev-tools`); release builds don't include these commands
+
+use std::fs;
+use std::path::Path;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{GrayImage, Luma};
+
+use crate::error::AppError;
+use crate::storage::write_atomic;
+use crate::task::run_blocking;
+
+/// Largest side generate_test_image accepts
+const MAX_TEST_IMAGE_DIMENSION: u32 = 10_000;
+
+/// Side of one checkerboard square, in pixels
+const CHECKER_SIZE: u32 = 32;
+
+/// Quality fixtures are saved at
+const TEST_IMAGE_QUALITY: u8 = 90;
+
+/// Fixed seed, so "noise" is the same image on every run and platform
+const NOISE_SEED: u64 = 0x005E_ED0F_7E57;
+
+/// SplitMix64 step: a tiny, well-distributed PRNG that needs no dependency
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Render `pattern` at `width`x`height` (grayscale, like stored receipts)
+fn render_pattern(pattern: &str, width: u32, height: u32) -> Result<GrayImage, AppError> {
+    let img = match pattern {
+        "solid" => GrayImage::from_pixel(width, height, Luma([200])),
+        // Left-to-right black to white
+        "gradient" => GrayImage::from_fn(width, height, |x, _| {
+            Luma([(u64::from(x) * 255 / u64::from(width.saturating_sub(1).max(1))) as u8])
+        }),
+        "checkerboard" => GrayImage::from_fn(width, height, |x, y| {
+            Luma([if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) { 0 } else { 255 }])
+        }),
+        "noise" => {
+            let mut state = NOISE_SEED;
+            let mut img = GrayImage::new(width, height);
+            for pixel in img.pixels_mut() {
+                pixel.0[0] = splitmix64(&mut state) as u8;
+            }
+            img
+        }
+        other => {
+            return Err(AppError::InvalidArgument {
+                reason: format!("Unknown pattern: {} (expected solid, gradient, checkerboard or noise)", other),
+            })
+        }
+    };
+    Ok(img)
+}
+
+/// Write a synthetic JPEG of exactly `width`x`height` to `dest` (absolute path; parent
+/// folders are created), for integration tests and reproducing bug reports without
+/// shipping binary fixtures
+/// `pattern` is "solid", "gradient", "checkerboard" (32px squares) or "noise" (fixed
+/// seed); the same inputs always give the same file. Returns the written path
+#[tauri::command]
+pub async fn generate_test_image(width: u32, height: u32, pattern: String, dest: String) -> Result<String, AppError> {
+    for (name, value) in [("width", width), ("height", height)] {
+        if !(1..=MAX_TEST_IMAGE_DIMENSION).contains(&value) {
+            return Err(AppError::InvalidArgument {
+                reason: format!("Invalid {}: {} (expected 1-{})", name, value, MAX_TEST_IMAGE_DIMENSION),
+            });
+        }
+    }
+    let path = Path::new(&dest).to_path_buf();
+    if !path.is_absolute() {
+        return Err(AppError::InvalidArgument { reason: format!("dest must be an absolute path: {}", dest) });
+    }
+
+    run_blocking(move || {
+        let img = render_pattern(&pattern, width, height)?;
+        let mut encoded = Vec::new();
+        JpegEncoder::new_with_quality(&mut encoded, TEST_IMAGE_QUALITY)
+            .encode_image(&img)
+            .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to encode test image: {}", e) })?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| AppError::io("Failed to create destination folder", e))?;
+        }
+        write_atomic(&path, &encoded).map_err(|e| AppError::io("Failed to write test image", e))?;
+        Ok(path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_have_exact_size_and_are_deterministic() {
+        for pattern in ["solid", "gradient", "checkerboard", "noise"] {
+            let img = render_pattern(pattern, 37, 5).unwrap();
+            assert_eq!(img.dimensions(), (37, 5));
+            assert_eq!(img, render_pattern(pattern, 37, 5).unwrap());
+        }
+        assert!(render_pattern("stripes", 10, 10).is_err());
+    }
+}
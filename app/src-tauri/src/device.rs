@@ -0,0 +1,81 @@
+//! Device identification and system context for bug reports
+
+use std::fs;
+
+use crate::error::AppError;
+use crate::storage::{get_app_home_dir, write_atomic};
+
+/// Machine identifier returned to the frontend
+#[derive(serde::Serialize)]
+pub struct MachineId {
+    pub id: String,
+    /// true when the OS ID was unavailable and a persisted random UUID is used instead
+    pub is_fallback: bool,
+}
+
+/// Read the persisted fallback ID, generating and saving a UUIDv4 on first use
+/// Stored at ~/.yorutsuke/machine-id so it stays stable across restarts
+fn fallback_machine_id() -> Result<String, AppError> {
+    let path = get_app_home_dir().join("machine-id");
+    if let Ok(existing) = fs::read_to_string(&path) {
+        let existing = existing.trim();
+        if !existing.is_empty() {
+            return Ok(existing.to_string());
+        }
+    }
+
+    let generated = uuid::Uuid::new_v4().to_string();
+    write_atomic(&path, generated.as_bytes())
+        .map_err(|e| AppError::io("Failed to persist fallback machine ID", e))?;
+    Ok(generated)
+}
+
+/// Get the machine's unique identifier
+/// Uses OS-level machine ID that persists across app reinstalls
+/// - macOS: IOPlatformUUID
+/// - Linux: /etc/machine-id
+/// - Windows: MachineGuid from registry
+///
+/// Falls back to a generated UUID when the OS ID can't be read (e.g. locked-down containers)
+#[tauri::command]
+pub fn get_machine_id() -> Result<MachineId, AppError> {
+    match machine_uid::get() {
+        Ok(id) => Ok(MachineId { id, is_fallback: false }),
+        Err(_) => Ok(MachineId { id: fallback_machine_id()?, is_fallback: true }),
+    }
+}
+
+/// OS and hardware context for the diagnostics panel (nothing user-identifying)
+#[derive(serde::Serialize)]
+pub struct SystemInfo {
+    /// `std::env::consts::OS` ("macos", "windows", "linux")
+    pub os: String,
+    /// e.g. "14.2" on macOS or "10.0.22631" on Windows; None when the OS doesn't report it
+    pub os_version: Option<String>,
+    /// `std::env::consts::ARCH` ("aarch64", "x86_64")
+    pub arch: String,
+    /// Logical CPUs available to the app (what rayon sizes its pool from)
+    pub cpu_count: usize,
+    /// Bytes
+    pub total_memory: u64,
+    /// Bytes
+    pub available_memory: u64,
+    pub hostname: Option<String>,
+}
+
+/// Get OS version, architecture, CPU count and memory for bug reports
+/// Complements get_machine_id; the username is deliberately not included
+#[tauri::command]
+pub fn get_system_info() -> SystemInfo {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    SystemInfo {
+        os: std::env::consts::OS.to_string(),
+        os_version: sysinfo::System::os_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        cpu_count: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        total_memory: system.total_memory(),
+        available_memory: system.available_memory(),
+        hostname: sysinfo::System::host_name(),
+    }
+}
@@ -0,0 +1,97 @@
+//! Structured errors returned by Tauri commands
+//! Serialized as `{ code, message }` so the frontend can branch on `code`
+//! and keep `message` for logging
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use serde::ser::SerializeStruct;
+
+#[derive(Debug)]
+pub enum AppError {
+    FileNotFound { path: String },
+    DecodeFailed { reason: String },
+    EncodeFailed { reason: String },
+    IoError { reason: String },
+    UnsupportedFormat { format: String },
+    InvalidArgument { reason: String },
+    InsufficientSpace { needed: u64, available: u64 },
+    OpenFailed { reason: String },
+    InvalidId { id: String },
+    DecryptFailed { reason: String },
+    ConfigInvalid { reason: String },
+    AlreadyExists { path: String },
+}
+
+impl AppError {
+    /// Wrap an I/O error with a short description of what was being attempted
+    pub fn io(context: &str, err: io::Error) -> Self {
+        AppError::IoError {
+            reason: format!("{}: {}", context, err),
+        }
+    }
+
+    /// Like `io`, but reports a missing file as `FileNotFound`
+    pub fn io_at(path: &Path, context: &str, err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::NotFound {
+            AppError::FileNotFound {
+                path: path.to_string_lossy().to_string(),
+            }
+        } else {
+            AppError::io(context, err)
+        }
+    }
+
+    /// Stable machine-readable code (never change existing values)
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::FileNotFound { .. } => "FILE_NOT_FOUND",
+            AppError::DecodeFailed { .. } => "DECODE_FAILED",
+            AppError::EncodeFailed { .. } => "ENCODE_FAILED",
+            AppError::IoError { .. } => "IO_ERROR",
+            AppError::UnsupportedFormat { .. } => "UNSUPPORTED_FORMAT",
+            AppError::InvalidArgument { .. } => "INVALID_ARGUMENT",
+            AppError::InsufficientSpace { .. } => "INSUFFICIENT_SPACE",
+            AppError::OpenFailed { .. } => "OPEN_FAILED",
+            AppError::InvalidId { .. } => "INVALID_ID",
+            AppError::DecryptFailed { .. } => "DECRYPT_FAILED",
+            AppError::ConfigInvalid { .. } => "CONFIG_INVALID",
+            AppError::AlreadyExists { .. } => "ALREADY_EXISTS",
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::FileNotFound { path } => write!(f, "File not found: {}", path),
+            AppError::DecodeFailed { reason } => write!(f, "{}", reason),
+            AppError::EncodeFailed { reason } => write!(f, "{}", reason),
+            AppError::IoError { reason } => write!(f, "{}", reason),
+            AppError::UnsupportedFormat { format } => write!(f, "Unsupported format: {}", format),
+            AppError::InvalidArgument { reason } => write!(f, "{}", reason),
+            AppError::InsufficientSpace { needed, available } => write!(
+                f,
+                "Insufficient disk space: need {} bytes, {} available",
+                needed, available
+            ),
+            AppError::OpenFailed { reason } => write!(f, "{}", reason),
+            AppError::InvalidId { id } => write!(f, "Invalid image id: {:?}", id),
+            AppError::DecryptFailed { reason } => write!(f, "{}", reason),
+            AppError::ConfigInvalid { reason } => write!(f, "Invalid config: {}", reason),
+            AppError::AlreadyExists { path } => write!(f, "Already exists: {}", path),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl serde::Serialize for AppError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
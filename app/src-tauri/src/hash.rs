@@ -0,0 +1,321 @@
+//! Streaming file hashes (duplicate detection and content identity)
+//! plus a perceptual hash for near-duplicate detection
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use image::imageops::FilterType;
+use sha2::{Digest, Sha256};
+
+use crate::compress::{open_oriented, target_dimensions, DEFAULT_MAX_DIMENSION};
+use crate::error::AppError;
+use crate::probe::sniff_format;
+use crate::task::run_blocking;
+
+/// Hash algorithms supported by `get_image_hash`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    /// Parse the `algorithm` argument from the frontend (defaults to MD5)
+    pub fn parse(algorithm: Option<&str>) -> Result<Self, AppError> {
+        match algorithm.unwrap_or("md5") {
+            "md5" => Ok(HashAlgorithm::Md5),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            other => Err(AppError::InvalidArgument {
+                reason: format!("Unsupported hash algorithm: {} (expected md5 or sha256)", other),
+            }),
+        }
+    }
+}
+
+/// Read buffer size for streaming hashes (keeps memory flat for large source images)
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Feed a file to `consume` in fixed-size chunks instead of reading it whole
+fn read_chunks(path: &Path, mut consume: impl FnMut(&[u8])) -> Result<(), AppError> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| AppError::io_at(path, "Failed to read file", e))?;
+    let mut buffer = vec![0u8; HASH_CHUNK_SIZE];
+
+    loop {
+        let read = file.read(&mut buffer)
+            .map_err(|e| AppError::io("Failed to read file", e))?;
+        if read == 0 {
+            return Ok(());
+        }
+        consume(&buffer[..read]);
+    }
+}
+
+/// Hash a file in fixed-size chunks, returning the lowercase hex digest
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<String, AppError> {
+    match algorithm {
+        HashAlgorithm::Md5 => {
+            let mut ctx = md5::Context::new();
+            read_chunks(path, |chunk| ctx.consume(chunk))?;
+            Ok(format!("{:x}", ctx.compute()))
+        }
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            read_chunks(path, |chunk| hasher.update(chunk))?;
+            Ok(format!("{:x}", hasher.finalize()))
+        }
+    }
+}
+
+/// Writer that MD5-hashes everything passing through to `inner`
+/// Lets an encoder write its output and hash it in one pass, with no read-back
+pub struct HashingWriter<W: Write> {
+    inner: W,
+    context: md5::Context,
+    bytes_written: u64,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        HashingWriter { inner, context: md5::Context::new(), bytes_written: 0 }
+    }
+
+    /// The inner writer, the lowercase hex MD5 and the byte count of everything written
+    pub fn finish(self) -> (W, String, u64) {
+        (self.inner, format!("{:x}", self.context.compute()), self.bytes_written)
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // Only hash what the inner writer accepted, so short writes stay in sync
+        let written = self.inner.write(buf)?;
+        self.context.consume(&buf[..written]);
+        self.bytes_written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Get hash of a file (for duplicate detection without compression)
+/// `algorithm` is "md5" (default) or "sha256"; the file is streamed, not loaded whole
+#[tauri::command]
+pub fn get_image_hash(path: String, algorithm: Option<String>) -> Result<String, AppError> {
+    let algorithm = HashAlgorithm::parse(algorithm.as_deref())?;
+    hash_file(Path::new(&path), algorithm)
+}
+
+/// Result of verify_file_hash
+#[derive(serde::Serialize)]
+pub struct HashVerification {
+    pub matches: bool,
+    /// Lowercase hex digest of the file
+    pub actual: String,
+}
+
+/// Check a file (e.g. an image downloaded during sync) against an expected digest
+/// `algorithm` is "md5" (default) or "sha256"; the comparison ignores case and
+/// surrounding whitespace. A missing file is FILE_NOT_FOUND, not `matches: false`
+#[tauri::command]
+pub fn verify_file_hash(
+    path: String,
+    expected: String,
+    algorithm: Option<String>,
+) -> Result<HashVerification, AppError> {
+    let algorithm = HashAlgorithm::parse(algorithm.as_deref())?;
+    let actual = hash_file(Path::new(&path), algorithm)?;
+    Ok(HashVerification { matches: actual.eq_ignore_ascii_case(expected.trim()), actual })
+}
+
+/// dHash grid: 9 columns so each of the 8 rows yields 8 left/right comparisons
+const DHASH_WIDTH: u32 = 9;
+const DHASH_HEIGHT: u32 = 8;
+
+/// Compute a 64-bit difference hash (dHash) of the decoded, upright image
+/// The image is shrunk to 9x8 grayscale and each bit records whether a pixel
+/// is brighter than its right-hand neighbour
+/// Bit layout: row-major from the top-left, row 0 column 0 is the most
+/// significant bit; returned as 16 lowercase hex digits
+pub fn perceptual_hash(path: &Path) -> Result<u64, AppError> {
+    let format = sniff_format(path)?;
+    let img = open_oriented(path, format)?;
+    let small = img
+        .resize_exact(DHASH_WIDTH, DHASH_HEIGHT, FilterType::Triangle)
+        .to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..DHASH_HEIGHT {
+        for x in 0..DHASH_WIDTH - 1 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    Ok(hash)
+}
+
+/// Get the perceptual hash of an original (uncompressed) image
+/// Visually similar photos (same receipt, slightly different angle) produce
+/// hashes with a small Hamming distance; see `hamming_distance`
+#[tauri::command]
+pub fn get_perceptual_hash(path: String) -> Result<String, AppError> {
+    let hash = perceptual_hash(Path::new(&path))?;
+    Ok(format!("{:016x}", hash))
+}
+
+/// Parse a hex hash produced by `get_perceptual_hash`
+fn parse_perceptual_hash(hex: &str) -> Result<u64, AppError> {
+    u64::from_str_radix(hex, 16).map_err(|_| AppError::InvalidArgument {
+        reason: format!("Invalid perceptual hash: {} (expected 16 hex digits)", hex),
+    })
+}
+
+/// Count differing bits between two perceptual hashes
+/// Roughly: 0-5 likely the same image, above 10 likely different
+#[tauri::command]
+pub fn hamming_distance(a: String, b: String) -> Result<u32, AppError> {
+    let a = parse_perceptual_hash(&a)?;
+    let b = parse_perceptual_hash(&b)?;
+    Ok((a ^ b).count_ones())
+}
+
+/// Version tag mixed into content ids; bump (and migrate the cloud) if the recipe changes
+const CONTENT_ID_VERSION: &[u8] = b"yorutsuke-content-v1";
+
+/// Hex characters kept from the SHA-256 (64 bits)
+const CONTENT_ID_LEN: usize = 16;
+
+/// Content id of an image: SHA-256 over the grayscale pixels the compress pipeline
+/// would encode, truncated to 16 hex characters
+///
+/// The recipe is: decode, apply EXIF orientation, resize (Lanczos3) so the longest
+/// side is at most 1536px (never upscaled), convert to 8-bit luma, then hash
+/// `CONTENT_ID_VERSION || width (u32 LE) || height (u32 LE) || row-major pixels`.
+/// Hashing after the resize and before JPEG encoding means the id doesn't depend
+/// on the output quality/format or on encoder version, and the same file yields the
+/// same id on every device. It is still an exact hash: a source that was itself
+/// re-saved gets a new id (use perceptual_hash for near-duplicates).
+/// The cloud must compute ids with exactly this recipe
+pub fn content_id(path: &Path) -> Result<String, AppError> {
+    let img = open_oriented(path, sniff_format(path)?)?;
+    let (width, height) = target_dimensions(img.width(), img.height(), DEFAULT_MAX_DIMENSION);
+    let img = if (width, height) != (img.width(), img.height()) {
+        img.resize_exact(width, height, FilterType::Lanczos3)
+    } else {
+        img
+    };
+    let luma = img.to_luma8();
+
+    let mut hasher = Sha256::new();
+    hasher.update(CONTENT_ID_VERSION);
+    hasher.update(luma.width().to_le_bytes());
+    hasher.update(luma.height().to_le_bytes());
+    hasher.update(luma.as_raw());
+    let mut id = format!("{:x}", hasher.finalize());
+    id.truncate(CONTENT_ID_LEN);
+    Ok(id)
+}
+
+/// Compute the device-independent content id of a source image (see content_id)
+/// Usable directly as an image id (16 lowercase hex characters)
+#[tauri::command]
+pub async fn compute_content_id(input_path: String) -> Result<String, AppError> {
+    run_blocking(move || {
+        let path = Path::new(&input_path);
+        if !path.exists() {
+            return Err(AppError::FileNotFound { path: input_path.clone() });
+        }
+        content_id(path)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a deterministic multi-megabyte file (not a multiple of the chunk size)
+    fn write_fixture(name: &str) -> std::path::PathBuf {
+        let data: Vec<u8> = (0..5 * 1024 * 1024 + 123)
+            .map(|i: usize| ((i * 31 + 7) % 251) as u8)
+            .collect();
+        let path = std::env::temp_dir().join(format!("yorutsuke-{}-{}", name, std::process::id()));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn streaming_md5_matches_precomputed_digest() {
+        let path = write_fixture("md5-fixture");
+        let hash = hash_file(&path, HashAlgorithm::Md5).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(hash, "9442dff95c87d9ff204eeb1deceee2e4");
+    }
+
+    #[test]
+    fn hashing_writer_matches_two_pass_digest() {
+        let path = write_fixture("hashing-writer-source");
+        let data = fs::read(&path).unwrap();
+        let out_path = path.with_extension("out");
+        let mut writer = HashingWriter::new(std::io::BufWriter::new(fs::File::create(&out_path).unwrap()));
+        for chunk in data.chunks(7919) {
+            writer.write_all(chunk).unwrap();
+        }
+        let (inner, hash, bytes_written) = writer.finish();
+        drop(inner.into_inner().unwrap());
+
+        assert_eq!(bytes_written, data.len() as u64);
+        assert_eq!(hash, format!("{:x}", md5::compute(&data)));
+        assert_eq!(hash, hash_file(&out_path, HashAlgorithm::Md5).unwrap());
+        fs::remove_file(&path).ok();
+        fs::remove_file(&out_path).ok();
+    }
+
+    #[test]
+    fn streaming_sha256_matches_precomputed_digest() {
+        let path = write_fixture("sha256-fixture");
+        let hash = hash_file(&path, HashAlgorithm::Sha256).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(hash, "a555efa808f6411d6849f3ecbcddb76f1278843104e0b48057476d06ce8423c1");
+    }
+
+    #[test]
+    fn content_id_ignores_lossless_container() {
+        let img = image::RgbImage::from_fn(64, 48, |x, y| image::Rgb([(x * 4) as u8, (y * 5) as u8, 128]));
+        let dir = std::env::temp_dir();
+        let png = dir.join(format!("yorutsuke-content-id-{}.png", std::process::id()));
+        let bmp = dir.join(format!("yorutsuke-content-id-{}.bmp", std::process::id()));
+        img.save(&png).unwrap();
+        img.save(&bmp).unwrap();
+
+        let png_id = content_id(&png).unwrap();
+        let bmp_id = content_id(&bmp).unwrap();
+        fs::remove_file(&png).ok();
+        fs::remove_file(&bmp).ok();
+        assert_eq!(png_id.len(), 16);
+        assert_eq!(png_id, bmp_id);
+    }
+
+    #[test]
+    fn verify_file_hash_ignores_case() {
+        let path = write_fixture("verify-fixture");
+        let path_str = path.to_string_lossy().to_string();
+        let upper = verify_file_hash(path_str.clone(), "9442DFF95C87D9FF204EEB1DECEEE2E4".to_string(), None).unwrap();
+        let wrong = verify_file_hash(path_str, "0".repeat(32), None).unwrap();
+        fs::remove_file(&path).ok();
+        assert!(upper.matches);
+        assert!(!wrong.matches);
+        assert_eq!(wrong.actual, "9442dff95c87d9ff204eeb1deceee2e4");
+    }
+
+    #[test]
+    fn missing_file_is_reported_as_file_not_found() {
+        let path = std::env::temp_dir().join("yorutsuke-hash-missing-fixture");
+        let err = hash_file(&path, HashAlgorithm::Md5).unwrap_err();
+        assert_eq!(err.code(), "FILE_NOT_FOUND");
+    }
+}
@@ -0,0 +1,139 @@
+//! Bulk import discovery: find receipt images under a user-chosen folder
+//! Discovery only; the frontend feeds the returned paths to compress_images
+
+use std::path::{Path, PathBuf};
+
+use walkdir::{DirEntry, WalkDir};
+
+use crate::error::AppError;
+use crate::storage::{get_app_home_dir, storage_root};
+use crate::task::run_blocking;
+
+/// Extensions matched when none are given (case-insensitive)
+const DEFAULT_IMPORT_EXTENSIONS: &[&str] =
+    &["jpg", "jpeg", "png", "webp", "gif", "bmp", "tif", "tiff", "heic", "heif"];
+
+/// Default and largest allowed `max_files`
+const DEFAULT_MAX_IMPORT_FILES: usize = 5_000;
+const MAX_IMPORT_FILES: usize = 50_000;
+
+/// Dot-files/-folders everywhere, plus the hidden attribute on Windows
+fn is_hidden(entry: &DirEntry) -> bool {
+    if entry.file_name().to_str().is_some_and(|name| name.starts_with('.')) {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if entry.metadata().is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Lowercase, without a leading dot ("JPG" and ".jpg" both mean "jpg")
+fn normalize_extensions(extensions: Option<Vec<String>>) -> Result<Vec<String>, AppError> {
+    let extensions: Vec<String> = match extensions {
+        Some(list) => list
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect(),
+        None => DEFAULT_IMPORT_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+    };
+    if extensions.is_empty() {
+        return Err(AppError::InvalidArgument { reason: "No file extensions given".to_string() });
+    }
+    Ok(extensions)
+}
+
+/// Directories the scan must not descend into: the app's own state and image store
+/// (importing our compressed output would duplicate every receipt)
+fn excluded_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![get_app_home_dir()];
+    if let Ok(root) = storage_root() {
+        dirs.push(root);
+    }
+    dirs.into_iter().filter_map(|dir| dir.canonicalize().ok()).collect()
+}
+
+fn is_excluded(path: &Path, excluded: &[PathBuf]) -> bool {
+    path.canonicalize().is_ok_and(|path| excluded.contains(&path))
+}
+
+/// List image files under `root` for a one-shot import
+/// - `recursive` (default true): descend into subfolders; symlinks are not followed
+/// - `extensions`: case-insensitive, default common image types incl. HEIC
+/// - `max_files` (default 5000, at most 50000): the scan stops once this many are
+///   found, so a result of exactly `max_files` paths may be incomplete
+///
+/// Hidden files and folders are skipped, as is the app's data directory if it lies
+/// under `root`. Unreadable subfolders are skipped rather than failing the scan.
+/// Paths are returned in a stable order (sorted by name within each folder)
+#[tauri::command]
+pub async fn import_directory(
+    root: String,
+    recursive: Option<bool>,
+    extensions: Option<Vec<String>>,
+    max_files: Option<usize>,
+) -> Result<Vec<String>, AppError> {
+    let extensions = normalize_extensions(extensions)?;
+    let max_files = max_files.unwrap_or(DEFAULT_MAX_IMPORT_FILES);
+    if !(1..=MAX_IMPORT_FILES).contains(&max_files) {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid max_files: {} (expected 1-{})", max_files, MAX_IMPORT_FILES),
+        });
+    }
+
+    run_blocking(move || {
+        let root_path = Path::new(&root);
+        if !root_path.exists() {
+            return Err(AppError::FileNotFound { path: root.clone() });
+        }
+        if !root_path.is_dir() {
+            return Err(AppError::InvalidArgument { reason: format!("Not a directory: {}", root) });
+        }
+
+        let excluded = excluded_dirs();
+        let max_depth = if recursive.unwrap_or(true) { usize::MAX } else { 1 };
+        let walker = WalkDir::new(root_path)
+            .max_depth(max_depth)
+            .sort_by_file_name()
+            .into_iter()
+            // The root itself may be hidden (e.g. chosen by path); only its contents are filtered
+            .filter_entry(|entry| {
+                entry.depth() == 0
+                    || !(is_hidden(entry) || (entry.file_type().is_dir() && is_excluded(entry.path(), &excluded)))
+            });
+
+        let mut found = Vec::new();
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    tracing::debug!(error = %e, "import_scan_skipped");
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let matches = entry
+                .path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.iter().any(|wanted| wanted.eq_ignore_ascii_case(ext)));
+            if matches {
+                found.push(entry.path().to_string_lossy().to_string());
+                if found.len() == max_files {
+                    tracing::warn!(max_files, "import_scan_truncated");
+                    break;
+                }
+            }
+        }
+        Ok(found)
+    })
+    .await
+}
@@ -1,260 +1,32 @@
-use std::fs::{self, OpenOptions};
-use std::io::Write;
-use std::path::Path;
-use chrono::{Local, Duration};
-use image::GenericImageView;
-use image::codecs::jpeg::JpegEncoder;
-
-/// Get the app's data directory for storing compressed images
-/// Uses platform-standard data directory for permanent local storage
-/// - macOS: ~/Library/Application Support/yorutsuke-v2/images/
-/// - Linux: ~/.local/share/yorutsuke-v2/images/
-/// - Windows: C:\Users\<user>\AppData\Local\yorutsuke-v2\images\
-fn get_data_dir() -> std::path::PathBuf {
-    let base = dirs::data_local_dir()
-        .or_else(dirs::data_dir)
-        .unwrap_or_else(|| dirs::home_dir().unwrap_or_else(std::env::temp_dir));
-    let images_dir = base.join("yorutsuke-v2").join("images");
-    fs::create_dir_all(&images_dir).ok();
-    images_dir
-}
-
-/// Compression result returned to frontend
-#[derive(serde::Serialize)]
-pub struct CompressResult {
-    pub success: bool,
-    pub id: String,
-    pub original_path: String,
-    pub output_path: String,
-    pub original_size: u64,
-    pub compressed_size: u64,
-    pub width: u32,
-    pub height: u32,
-    pub md5: String,
-}
-
-/// Compress an image: resize to max 1536px, convert to grayscale, JPEG 75%
-/// Grayscale conversion reduces file size by ~60% while maintaining OCR quality
-#[tauri::command]
-fn compress_image(input_path: String, image_id: String) -> Result<CompressResult, String> {
-    let path = Path::new(&input_path);
-    if !path.exists() {
-        return Err(format!("File not found: {}", input_path));
-    }
-
-    // Get original file size
-    let original_size = fs::metadata(path)
-        .map_err(|e| format!("Failed to read metadata: {}", e))?
-        .len();
-
-    // Load image
-    let img = image::open(path)
-        .map_err(|e| format!("Failed to open image: {}", e))?;
-
-    let (orig_width, orig_height) = img.dimensions();
-
-    // Calculate new dimensions (max 1536px on longest side)
-    const MAX_SIZE: u32 = 1536;
-    let (new_width, new_height) = if orig_width > orig_height {
-        if orig_width > MAX_SIZE {
-            let ratio = MAX_SIZE as f32 / orig_width as f32;
-            (MAX_SIZE, (orig_height as f32 * ratio) as u32)
-        } else {
-            (orig_width, orig_height)
-        }
-    } else if orig_height > MAX_SIZE {
-        let ratio = MAX_SIZE as f32 / orig_height as f32;
-        ((orig_width as f32 * ratio) as u32, MAX_SIZE)
-    } else {
-        (orig_width, orig_height)
-    };
-
-    // Resize if needed
-    let resized = if new_width != orig_width || new_height != orig_height {
-        img.resize_exact(new_width, new_height, image::imageops::FilterType::Lanczos3)
-    } else {
-        img
-    };
-
-    // Output path
-    let output_path = get_data_dir().join(format!("{}.jpg", image_id));
-
-    // Convert to grayscale then to RGB8 for JPEG encoding
-    // Grayscale reduces file size significantly while maintaining OCR quality
-    let grayscale = resized.grayscale();
-    let rgb_image = grayscale.to_rgb8();
-
-    // Get actual dimensions
-    let actual_width = rgb_image.width();
-    let actual_height = rgb_image.height();
-
-    // Encode to JPEG (75% quality - balanced for OCR and file size)
-    let file = fs::File::create(&output_path)
-        .map_err(|e| format!("Failed to create output file: {}", e))?;
-    let mut encoder = JpegEncoder::new_with_quality(file, 75);
-    encoder.encode(&rgb_image, actual_width, actual_height, image::ExtendedColorType::Rgb8)
-        .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
-
-    // Calculate MD5 hash of compressed data (for duplicate detection)
-    let jpeg_data = fs::read(&output_path)
-        .map_err(|e| format!("Failed to read JPEG file: {}", e))?;
-    let md5_hash = format!("{:x}", md5::compute(&jpeg_data));
-
-    // Get compressed file size
-    let compressed_size = fs::metadata(&output_path)
-        .map_err(|e| format!("Failed to get file size: {}", e))?
-        .len();
-    let output_path_str = output_path.to_string_lossy().to_string();
-
-    Ok(CompressResult {
-        success: true,
-        id: image_id,
-        original_path: input_path,
-        output_path: output_path_str,
-        original_size,
-        compressed_size,
-        width: actual_width,
-        height: actual_height,
-        md5: md5_hash,
-    })
-}
-
-/// Get MD5 hash of a file (for duplicate detection without compression)
-#[tauri::command]
-fn get_image_hash(path: String) -> Result<String, String> {
-    let data = fs::read(&path)
-        .map_err(|e| format!("Failed to read file: {}", e))?;
-    Ok(format!("{:x}", md5::compute(&data)))
-}
-
-/// Delete a local file
-#[tauri::command]
-fn delete_file(path: String) -> Result<(), String> {
-    let file_path = Path::new(&path);
-    if !file_path.exists() {
-        return Ok(()); // Not an error if file doesn't exist
-    }
-    fs::remove_file(file_path)
-        .map_err(|e| format!("Failed to delete file: {}", e))
-}
-
-// ============================================================================
-// Logging System (Pillar R: Observability)
-// ============================================================================
-
-/// Get the logs directory (~/.yorutsuke/logs/)
-fn get_logs_dir() -> std::path::PathBuf {
-    let home = dirs::home_dir().unwrap_or_else(|| std::env::temp_dir());
-    let logs_dir = home.join(".yorutsuke").join("logs");
-    fs::create_dir_all(&logs_dir).ok();
-    logs_dir
-}
-
-/// Log entry from frontend
-#[derive(serde::Deserialize)]
-pub struct LogEntry {
-    pub timestamp: String,
-    pub level: String,
-    pub event: String,
-    #[serde(rename = "traceId")]
-    pub trace_id: String,
-    #[serde(rename = "userId")]
-    pub user_id: Option<String>,
-    #[serde(flatten)]
-    pub extra: std::collections::HashMap<String, serde_json::Value>,
-}
-
-/// Write a log entry to the daily log file
-/// File format: ~/.yorutsuke/logs/YYYY-MM-DD.jsonl
-#[tauri::command]
-fn log_write(entry: LogEntry) -> Result<(), String> {
-    let logs_dir = get_logs_dir();
-    let today = Local::now().format("%Y-%m-%d").to_string();
-    let log_file = logs_dir.join(format!("{}.jsonl", today));
-
-    // Reconstruct the full JSON entry
-    let mut json_obj = serde_json::json!({
-        "timestamp": entry.timestamp,
-        "level": entry.level,
-        "event": entry.event,
-        "traceId": entry.trace_id,
-    });
-
-    if let Some(user_id) = &entry.user_id {
-        json_obj["userId"] = serde_json::json!(user_id);
-    }
-
-    // Merge extra fields
-    if let serde_json::Value::Object(ref mut map) = json_obj {
-        for (key, value) in entry.extra {
-            map.insert(key, value);
-        }
-    }
-
-    let json_line = serde_json::to_string(&json_obj)
-        .map_err(|e| format!("Failed to serialize log entry: {}", e))?;
-
-    // Append to file
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_file)
-        .map_err(|e| format!("Failed to open log file: {}", e))?;
-
-    writeln!(file, "{}", json_line)
-        .map_err(|e| format!("Failed to write log entry: {}", e))?;
-
-    Ok(())
-}
-
-/// Clean up log files older than retention days (default: 7)
-#[tauri::command]
-fn log_cleanup(retention_days: Option<i64>) -> Result<u32, String> {
-    let retention = retention_days.unwrap_or(7);
-    let logs_dir = get_logs_dir();
-    let cutoff = Local::now() - Duration::days(retention);
-    let cutoff_str = cutoff.format("%Y-%m-%d").to_string();
-
-    let mut deleted_count = 0u32;
-
-    let entries = fs::read_dir(&logs_dir)
-        .map_err(|e| format!("Failed to read logs directory: {}", e))?;
-
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-            // Only process .jsonl files with date format
-            if filename.ends_with(".jsonl") && filename.len() == 15 {
-                let date_part = &filename[..10]; // YYYY-MM-DD
-                if date_part < cutoff_str.as_str() {
-                    if fs::remove_file(&path).is_ok() {
-                        deleted_count += 1;
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(deleted_count)
-}
-
-/// Get the path to today's log file (for debugging)
-#[tauri::command]
-fn log_get_path() -> String {
-    let logs_dir = get_logs_dir();
-    let today = Local::now().format("%Y-%m-%d").to_string();
-    logs_dir.join(format!("{}.jsonl", today)).to_string_lossy().to_string()
-}
-
-/// Get the machine's unique identifier
-/// Uses OS-level machine ID that persists across app reinstalls
-/// - macOS: IOPlatformUUID
-/// - Linux: /etc/machine-id
-/// - Windows: MachineGuid from registry
-#[tauri::command]
-fn get_machine_id() -> Result<String, String> {
-    machine_uid::get().map_err(|e| format!("Failed to get machine ID: {}", e))
-}
+mod analysis;
+mod app_info;
+mod barcode;
+mod compress;
+mod config;
+mod contact_sheet;
+mod crypto;
+mod database;
+#[cfg(feature = "dev-tools")]
+mod dev_tools;
+mod device;
+mod error;
+mod hash;
+mod import;
+mod logging;
+mod pdf;
+mod probe;
+mod queue;
+mod quota;
+mod redact;
+mod stamp;
+mod split;
+mod storage;
+mod task;
+mod total_region;
+mod watch;
+mod webp;
+
+use tauri::Manager;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -263,6 +35,11 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    probe::register_decoders();
+    let config = config::read_config();
+    let log_writer = logging::LogWriter::new(config.log_date_basis);
+    logging::init_tracing(log_writer.clone());
+
     tauri::Builder::default()
         .plugin(tauri_plugin_sql::Builder::new().build())
         .plugin(tauri_plugin_http::init())
@@ -270,18 +47,105 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .invoke_handler(tauri::generate_handler![
             greet,
-            compress_image,
-            get_image_hash,
-            delete_file,
-            log_write,
-            log_cleanup,
-            log_get_path,
-            get_machine_id
+            analysis::score_image_sharpness,
+            analysis::detect_script_hint,
+            analysis::is_likely_receipt,
+            analysis::image_diff,
+            analysis::detect_rotation,
+            app_info::get_app_version,
+            app_info::get_runtime_diagnostics,
+            app_info::get_app_paths,
+            database::check_database,
+            database::backup_database,
+            database::list_database_backups,
+            database::maintain_database,
+            barcode::decode_receipt_codes,
+            compress::compress_image,
+            compress::compress_image_bytes,
+            compress::compress_images,
+            compress::cancel_compress_batch,
+            compress::import_image,
+            compress::rotate_stored_image,
+            compress::recompress_stored_image,
+            compress::recompress_stored_images,
+            compress::migrate_store_to_webp,
+            compress::read_image_base64,
+            compress::estimate_compressed_size,
+            compress::read_image_encrypted,
+            config::load_config,
+            config::save_config,
+            contact_sheet::generate_contact_sheet,
+            stamp::stamp_image,
+            total_region::extract_total_region,
+            split::split_receipts,
+            import::import_directory,
+            hash::get_image_hash,
+            hash::verify_file_hash,
+            hash::compute_content_id,
+            hash::get_perceptual_hash,
+            hash::hamming_distance,
+            pdf::export_images_pdf,
+            probe::probe_image,
+            probe::get_image_captured_at,
+            crypto::set_image_encryption,
+            queue::persist_compress_queue,
+            queue::load_compress_queue,
+            quota::record_upload,
+            quota::check_quota,
+            storage::delete_file,
+            storage::move_file,
+            storage::ensure_storage_ready,
+            storage::get_storage_root,
+            storage::set_storage_root,
+            storage::migrate_storage,
+            storage::get_images_dir_size,
+            storage::get_images_dir_path,
+            storage::open_images_dir,
+            storage::cleanup_orphan_images,
+            storage::dedup_store,
+            storage::list_stored_images,
+            storage::get_stored_image_stats,
+            storage::check_storage_health,
+            storage::scan_corrupt_images,
+            storage::wipe_local_data,
+            watch::images_watch_start,
+            watch::images_watch_stop,
+            logging::log_write,
+            logging::log_cleanup,
+            logging::get_log_retention,
+            logging::set_log_retention,
+            logging::log_get_path,
+            logging::open_logs_dir,
+            logging::log_tail_start,
+            logging::log_tail_stop,
+            logging::log_query,
+            logging::log_summary,
+            logging::export_logs,
+            logging::export_logs_merged,
+            logging::export_logs_csv,
+            device::get_machine_id,
+            device::get_system_info,
+            #[cfg(feature = "dev-tools")]
+            dev_tools::generate_test_image
         ])
-        .setup(|_app| {
+        .manage(log_writer)
+        .manage(logging::LogRateLimiter::new(
+            config.log_rate_limit.unwrap_or(logging::DEFAULT_LOG_RATE_LIMIT),
+        ))
+        .manage(logging::LogTail::default())
+        .manage(watch::ImagesWatch::default())
+        .manage(compress::CompressBatches::default())
+        .setup(|app| {
             // DevTools can be opened manually with Cmd+Option+I (macOS) or F12 (Windows/Linux)
+            logging::spawn_log_flusher(app.handle().clone());
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // Don't lose buffered log entries on shutdown
+            if let tauri::RunEvent::Exit = event {
+                app.state::<logging::LogWriter>().flush();
+            }
+        });
 }
@@ -0,0 +1,1376 @@
+//! Logging System (Pillar R: Observability)
+//! Frontend log entries are appended to daily JSONL files under ~/.yorutsuke/logs/
+//! Rust-side `tracing` spans and events are written to the same files (see `init_tracing`)
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, SecondsFormat, TimeZone, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::config::{read_config, update_config};
+use crate::error::AppError;
+use crate::redact;
+use crate::storage::{app_home_dir, open_in_file_manager};
+
+/// The logs directory (~/.yorutsuke/logs/), not created
+pub fn logs_dir() -> PathBuf {
+    app_home_dir().join("logs")
+}
+
+/// Get the logs directory, creating it if needed
+pub fn get_logs_dir() -> PathBuf {
+    let logs_dir = logs_dir();
+    fs::create_dir_all(&logs_dir).ok();
+    logs_dir
+}
+
+/// Clock log files are dated by (config `log_date_basis`)
+/// Local keeps a day's file matching the user's calendar; UTC stops travel and DST
+/// changes from splitting or merging days
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogDateBasis {
+    #[default]
+    Local,
+    Utc,
+}
+
+impl LogDateBasis {
+    /// Log date of the instant `now`, with `local` standing in for the system timezone
+    fn date_at<Tz: TimeZone>(self, now: DateTime<Utc>, local: &Tz) -> NaiveDate {
+        match self {
+            LogDateBasis::Local => now.with_timezone(local).date_naive(),
+            LogDateBasis::Utc => now.date_naive(),
+        }
+    }
+
+    /// Today's log date
+    pub fn today(self) -> NaiveDate {
+        self.date_at(Utc::now(), &Local)
+    }
+}
+
+/// Size at which a day's log rolls over to the next `{date}.{n}.jsonl` file
+const MAX_LOG_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Path of rotation `index` for `date` (`{date}.jsonl` for 0, `{date}.{n}.jsonl` otherwise)
+fn rotated_log_path(logs_dir: &Path, date: &str, index: u32) -> PathBuf {
+    if index == 0 {
+        logs_dir.join(format!("{}.jsonl", date))
+    } else {
+        logs_dir.join(format!("{}.{}.jsonl", date, index))
+    }
+}
+
+/// Rotation index encoded in a plain daily log filename for `date`
+fn rotation_index(filename: &str, date: &str) -> Option<u32> {
+    let rest = filename.strip_prefix(date)?.strip_suffix(".jsonl")?;
+    if rest.is_empty() {
+        return Some(0);
+    }
+    rest.strip_prefix('.')?.parse().ok()
+}
+
+/// All existing plain log files for `date`, oldest rotation first
+fn daily_log_files(logs_dir: &Path, date: &str) -> Vec<PathBuf> {
+    let mut indexes: Vec<u32> = fs::read_dir(logs_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str().and_then(|name| rotation_index(name, date)))
+        .collect();
+    indexes.sort_unstable();
+    indexes.into_iter().map(|index| rotated_log_path(logs_dir, date, index)).collect()
+}
+
+/// File that new entries for `date` should be appended to
+/// Uses the highest existing rotation, moving to the next one once it passes MAX_LOG_FILE_BYTES
+fn current_log_file(logs_dir: &Path, date: &str) -> PathBuf {
+    let highest = daily_log_files(logs_dir, date)
+        .last()
+        .and_then(|path| path.file_name()?.to_str().and_then(|name| rotation_index(name, date)))
+        .unwrap_or(0);
+    let path = rotated_log_path(logs_dir, date, highest);
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    if size >= MAX_LOG_FILE_BYTES {
+        rotated_log_path(logs_dir, date, highest + 1)
+    } else {
+        path
+    }
+}
+
+/// Log entry from frontend
+#[derive(serde::Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub event: String,
+    #[serde(rename = "traceId")]
+    pub trace_id: String,
+    #[serde(rename = "userId")]
+    pub user_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: std::collections::HashMap<String, serde_json::Value>,
+}
+
+/// How often buffered log lines are flushed when nothing else forces a flush
+const LOG_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Buffer capacity before BufWriter flushes on its own
+const LOG_BUFFER_BYTES: usize = 64 * 1024;
+
+/// The daily log file currently held open by LogWriter
+struct OpenLogFile {
+    date: String,
+    path: PathBuf,
+    writer: BufWriter<fs::File>,
+    size: u64,
+}
+
+impl OpenLogFile {
+    fn open(logs_dir: &Path, date: String) -> Result<Self, AppError> {
+        let path = current_log_file(logs_dir, &date);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| AppError::io("Failed to open log file", e))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(OpenLogFile {
+            date,
+            path,
+            writer: BufWriter::with_capacity(LOG_BUFFER_BYTES, file),
+            size,
+        })
+    }
+}
+
+/// Buffered appender for the daily log file (managed Tauri state)
+/// Keeps the file open between log_write calls instead of reopening it per entry;
+/// reopens on date rollover and when the file reaches the rotation size
+/// Clones share the same file, so the tracing layer and log_write never interleave partial lines
+#[derive(Default, Clone)]
+pub struct LogWriter {
+    current: Arc<Mutex<Option<OpenLogFile>>>,
+    /// Fixed at startup, so one run never switches clocks mid-file
+    date_basis: LogDateBasis,
+}
+
+impl LogWriter {
+    pub fn new(date_basis: LogDateBasis) -> Self {
+        LogWriter { current: Arc::default(), date_basis }
+    }
+
+    /// Today's date (YYYY-MM-DD) under the writer's date basis
+    fn today(&self) -> String {
+        self.date_basis.today().to_string()
+    }
+
+    /// Lock the writer, recovering from a panic in another holder (the file handle stays valid)
+    fn lock(&self) -> MutexGuard<'_, Option<OpenLogFile>> {
+        self.current.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Append one line to today's file
+    fn append_line(&self, line: &str) -> Result<(), AppError> {
+        let mut current = self.lock();
+        let today = self.today();
+
+        let needs_reopen = match current.as_ref() {
+            Some(open) => open.date != today || open.size >= MAX_LOG_FILE_BYTES,
+            None => true,
+        };
+        if needs_reopen {
+            // Flush first so current_log_file sees the real on-disk size when rotating
+            if let Some(mut previous) = current.take() {
+                previous.writer.flush().ok();
+            }
+            *current = Some(OpenLogFile::open(&get_logs_dir(), today)?);
+        }
+
+        let open = current.as_mut().expect("log file opened above");
+        writeln!(open.writer, "{}", line)
+            .map_err(|e| AppError::io("Failed to write log entry", e))?;
+        open.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// Record an event raised on the Rust side, in the same shape as frontend entries
+    /// Logging must never fail the operation being logged, so write errors are dropped
+    fn write_event(&self, level: &str, event: &str, trace_id: &str, fields: JsonFields) {
+        let mut json_obj = serde_json::json!({
+            "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            "level": level,
+            "event": event,
+            "traceId": trace_id,
+        });
+        if let serde_json::Value::Object(map) = &mut json_obj {
+            for (key, value) in fields.0 {
+                map.entry(key).or_insert(value);
+            }
+        }
+        if let Ok(line) = serde_json::to_string(&json_obj) {
+            self.append_line(&line).ok();
+        }
+    }
+
+    /// Flush buffered lines to disk
+    pub fn flush(&self) {
+        if let Some(open) = self.lock().as_mut() {
+            open.writer.flush().ok();
+        }
+    }
+
+    /// Flush and close the open file; the next entry reopens one
+    /// Lets the logs directory be deleted (Windows refuses to delete open files)
+    pub fn close(&self) {
+        if let Some(mut open) = self.lock().take() {
+            open.writer.flush().ok();
+        }
+    }
+
+    /// Path of the file currently being written, if one is open
+    fn current_path(&self) -> Option<PathBuf> {
+        self.lock().as_ref().map(|open| open.path.clone())
+    }
+}
+
+/// Flush the managed LogWriter every LOG_FLUSH_INTERVAL so quiet periods still reach disk
+/// Also writes the pending log_rate_limited summary once a flood has died down
+pub fn spawn_log_flusher(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(LOG_FLUSH_INTERVAL);
+        let writer = app.state::<LogWriter>();
+        app.state::<LogRateLimiter>().write_summary(&writer, Instant::now());
+        writer.flush();
+    });
+}
+
+/// Default cap on frontend log entries per second; far above normal use
+pub const DEFAULT_LOG_RATE_LIMIT: u32 = 500;
+
+/// Allowed `log_rate_limit` settings
+pub const LOG_RATE_LIMIT_RANGE: std::ops::RangeInclusive<u32> = 10..=100_000;
+
+/// Least time between two log_rate_limited summary lines
+const RATE_SUMMARY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Token bucket holding up to one second's worth of entries
+struct TokenBucket {
+    per_second: f64,
+    tokens: f64,
+    refilled_at: Instant,
+    /// Entries dropped since the last summary line
+    dropped: u64,
+    summarized_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_second: u32, now: Instant) -> Self {
+        let per_second = f64::from(per_second);
+        TokenBucket { per_second, tokens: per_second, refilled_at: now, dropped: 0, summarized_at: now }
+    }
+
+    /// Take a token if one is available, otherwise count the entry as dropped
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.per_second);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            self.dropped += 1;
+            false
+        }
+    }
+
+    /// Dropped count to report, if any, at most once per RATE_SUMMARY_INTERVAL
+    fn take_summary(&mut self, now: Instant) -> Option<u64> {
+        if self.dropped == 0 || now.saturating_duration_since(self.summarized_at) < RATE_SUMMARY_INTERVAL {
+            return None;
+        }
+        self.summarized_at = now;
+        Some(std::mem::take(&mut self.dropped))
+    }
+}
+
+/// Safety valve against runaway frontend logging filling the disk (managed Tauri state)
+/// log_write drops entries beyond `log_rate_limit` per second (config.json, default 500)
+/// and a `log_rate_limited { dropped_count }` warning is written at most every 5s instead
+pub struct LogRateLimiter {
+    bucket: Mutex<TokenBucket>,
+}
+
+impl LogRateLimiter {
+    pub fn new(per_second: u32) -> Self {
+        LogRateLimiter { bucket: Mutex::new(TokenBucket::new(per_second, Instant::now())) }
+    }
+
+    fn bucket(&self) -> MutexGuard<'_, TokenBucket> {
+        self.bucket.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Write the dropped-entries summary through `writer` if one is due
+    fn write_summary(&self, writer: &LogWriter, now: Instant) {
+        let Some(dropped) = self.bucket().take_summary(now) else { return };
+        let mut fields = JsonFields::default();
+        fields.0.insert("dropped_count".to_string(), dropped.into());
+        writer.write_event("warn", "log_rate_limited", NO_TRACE, fields);
+    }
+}
+
+/// Write a log entry to the daily log file
+/// File format: ~/.yorutsuke/logs/YYYY-MM-DD.jsonl, then YYYY-MM-DD.1.jsonl, ... once
+/// a file passes 10MB
+/// Entries are buffered; error-level entries are flushed immediately
+/// `extra` values are redacted and `userId` is hashed (see `redact`)
+/// Entries beyond the rate limit are dropped silently (see LogRateLimiter)
+#[tauri::command]
+pub fn log_write(
+    writer: State<'_, LogWriter>,
+    limiter: State<'_, LogRateLimiter>,
+    entry: LogEntry,
+) -> Result<(), AppError> {
+    let now = Instant::now();
+    if !limiter.bucket().try_acquire(now) {
+        return Ok(());
+    }
+    limiter.write_summary(&writer, now);
+    let is_error = entry.level == "error";
+
+    // Reconstruct the full JSON entry
+    let mut json_obj = serde_json::json!({
+        "timestamp": entry.timestamp,
+        "level": entry.level,
+        "event": entry.event,
+        "traceId": entry.trace_id,
+    });
+
+    // Strip PII before it reaches disk (users attach these logs to support requests)
+    let redact = redact::redaction_enabled();
+
+    if let Some(user_id) = &entry.user_id {
+        let user_id = if redact { redact::hash_user_id(user_id) } else { user_id.clone() };
+        json_obj["userId"] = serde_json::json!(user_id);
+    }
+
+    // Merge extra fields
+    if let serde_json::Value::Object(ref mut map) = json_obj {
+        for (key, mut value) in entry.extra {
+            if redact {
+                redact::redact_value(&mut value);
+            }
+            map.insert(key, value);
+        }
+    }
+
+    let json_line = serde_json::to_string(&json_obj)
+        .map_err(|e| AppError::InvalidArgument { reason: format!("Failed to serialize log entry: {}", e) })?;
+
+    writer.append_line(&json_line)?;
+    if is_error {
+        writer.flush();
+    }
+
+    Ok(())
+}
+
+/// Result of a log cleanup pass
+/// `freed_bytes` and `oldest_kept_date` were added alongside the original counts;
+/// existing callers reading only `deleted_count` are unaffected
+#[derive(serde::Serialize)]
+pub struct LogCleanupResult {
+    pub deleted_count: u32,
+    pub compressed_count: u32,
+    /// Total size of the deleted files (gzip savings are not counted)
+    pub freed_bytes: u64,
+    /// Earliest log date still on disk (YYYY-MM-DD), None when no log files remain
+    pub oldest_kept_date: Option<String>,
+}
+
+/// Extract the date from a daily log filename
+/// Accepts `{date}.jsonl`, rotated `{date}.{n}.jsonl` and their gzipped `.gz` forms, with
+/// the date exactly as the writer formats it (YYYY-MM-DD); returns `(date, is_gzipped)`
+fn parse_log_filename(filename: &str) -> Option<(NaiveDate, bool)> {
+    let (name, is_gzipped) = match filename.strip_suffix(".gz") {
+        Some(name) => (name, true),
+        None => (filename, false),
+    };
+    let (date_part, _) = name.split_once('.')?;
+    let date = NaiveDate::parse_from_str(date_part, "%Y-%m-%d").ok()?;
+    // chrono accepts unpadded fields, which the writer never produces
+    if date.to_string() != date_part {
+        return None;
+    }
+    rotation_index(name, date_part)?;
+    Some((date, is_gzipped))
+}
+
+/// Stream `path` into a gzip archive at `gz_path`
+fn write_gzip(path: &Path, gz_path: &Path) -> std::io::Result<()> {
+    let mut input = fs::File::open(path)?;
+    let mut encoder = GzEncoder::new(fs::File::create(gz_path)?, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()
+}
+
+/// Gzip `path` to `{path}.gz` and remove the original
+/// A partially written archive is removed on failure so the original stays authoritative
+fn gzip_log_file(path: &Path) -> std::io::Result<()> {
+    let mut gz_name = path.as_os_str().to_owned();
+    gz_name.push(".gz");
+    let gz_path = PathBuf::from(gz_name);
+
+    match write_gzip(path, &gz_path) {
+        Ok(()) => fs::remove_file(path),
+        Err(e) => {
+            fs::remove_file(&gz_path).ok();
+            Err(e)
+        }
+    }
+}
+
+/// Used when no retention has been saved with set_log_retention
+const DEFAULT_LOG_RETENTION_DAYS: u32 = 7;
+
+/// Allowed range for a saved log retention
+pub const LOG_RETENTION_RANGE: std::ops::RangeInclusive<u32> = 1..=365;
+
+/// Saved log retention, or the default when unset
+fn log_retention_days() -> u32 {
+    read_config().log_retention_days.unwrap_or(DEFAULT_LOG_RETENTION_DAYS)
+}
+
+/// Get the log retention log_cleanup applies by default (days)
+#[tauri::command]
+pub fn get_log_retention() -> u32 {
+    log_retention_days()
+}
+
+/// Save how many days of logs to keep (1-365), so cleanup on startup honors it
+#[tauri::command]
+pub fn set_log_retention(days: u32) -> Result<(), AppError> {
+    if !LOG_RETENTION_RANGE.contains(&days) {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid log retention: {} days (expected 1-365)", days),
+        });
+    }
+    update_config(|config| config.log_retention_days = Some(days))
+}
+
+/// Delete or compress the log files in `logs_dir` relative to the log date `today`
+/// Files dated before `today - retention` are deleted; with `compress_after`, plain files
+/// dated before `today - compress_after` are gzipped. Dates are compared as dates, not
+/// as filename strings
+fn cleanup_logs_in(
+    logs_dir: &Path,
+    today: NaiveDate,
+    retention: i64,
+    compress_after: Option<i64>,
+) -> Result<LogCleanupResult, AppError> {
+    let cutoff = today - Duration::days(retention);
+    let compress_cutoff = compress_after.map(|days| today - Duration::days(days));
+
+    let mut result = LogCleanupResult {
+        deleted_count: 0,
+        compressed_count: 0,
+        freed_bytes: 0,
+        oldest_kept_date: None,
+    };
+    let mut oldest_kept: Option<NaiveDate> = None;
+
+    let entries = fs::read_dir(logs_dir)
+        .map_err(|e| AppError::io("Failed to read logs directory", e))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(filename) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        // Only process daily log files
+        let Some((date, is_gzipped)) = parse_log_filename(filename) else { continue };
+
+        if date < cutoff {
+            // Stat first: the size is gone once the file is
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if fs::remove_file(&path).is_ok() {
+                result.deleted_count += 1;
+                result.freed_bytes += size;
+                continue;
+            }
+        } else if let Some(compress_cutoff) = compress_cutoff {
+            if !is_gzipped && date < compress_cutoff && gzip_log_file(&path).is_ok() {
+                result.compressed_count += 1;
+            }
+        }
+        if oldest_kept.is_none_or(|oldest| date < oldest) {
+            oldest_kept = Some(date);
+        }
+    }
+
+    result.oldest_kept_date = oldest_kept.map(|date| date.to_string());
+    Ok(result)
+}
+
+/// Clean up log files older than retention days (1-365; default: the saved setting, else 7)
+/// When `compress_after_days` is set, files older than that (but still retained)
+/// are gzipped to `{date}.jsonl.gz`; compressed files expire like plain ones
+/// Days are counted from today's log date, in the same date basis the files are named by
+#[tauri::command]
+pub fn log_cleanup(
+    writer: State<'_, LogWriter>,
+    retention_days: Option<i64>,
+    compress_after_days: Option<i64>,
+) -> Result<LogCleanupResult, AppError> {
+    let retention = match retention_days {
+        Some(days) if !u32::try_from(days).is_ok_and(|days| LOG_RETENTION_RANGE.contains(&days)) => {
+            return Err(AppError::InvalidArgument {
+                reason: format!("Invalid retention_days: {} (expected 1-365)", days),
+            });
+        }
+        Some(days) => days,
+        None => i64::from(log_retention_days()),
+    };
+    // Today's file is still being appended to, so it is never compressed
+    if let Some(days) = compress_after_days {
+        if days < 1 {
+            return Err(AppError::InvalidArgument {
+                reason: format!("Invalid compress_after_days: {} (expected >= 1)", days),
+            });
+        }
+    }
+    cleanup_logs_in(&get_logs_dir(), writer.date_basis.today(), retention, compress_after_days)
+}
+
+/// Get the path to today's log file (for debugging)
+/// Reports the rotation currently being written to
+#[tauri::command]
+pub fn log_get_path(writer: State<'_, LogWriter>) -> String {
+    writer.flush();
+    let path = writer.current_path().unwrap_or_else(|| current_log_file(&get_logs_dir(), &writer.today()));
+    path.to_string_lossy().to_string()
+}
+
+/// Parse a YYYY-MM-DD date argument from the frontend
+/// Parse rather than trust the string, since dates become part of file paths
+fn parse_log_date(date: &str) -> Result<NaiveDate, AppError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| AppError::InvalidArgument {
+        reason: format!("Invalid log date: {} (expected YYYY-MM-DD)", date),
+    })
+}
+
+/// Normalize an optional YYYY-MM-DD argument, defaulting to the writer's today
+fn resolve_log_date(writer: &LogWriter, date: Option<String>) -> Result<String, AppError> {
+    match date {
+        Some(date) => Ok(parse_log_date(&date)?.format("%Y-%m-%d").to_string()),
+        None => Ok(writer.today()),
+    }
+}
+
+/// Open the logs directory in the OS file manager (so users can attach logs to a report)
+/// The directory is created first, so this works on a fresh install
+#[tauri::command]
+pub fn open_logs_dir() -> Result<(), AppError> {
+    open_in_file_manager(&get_logs_dir())
+}
+
+/// How often log_tail_start checks for new lines
+const LOG_TAIL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Stop flag of the running log tail, if any (managed Tauri state)
+#[derive(Default)]
+pub struct LogTail {
+    active: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+impl LogTail {
+    /// Signal the running tail (if any) to stop
+    fn stop(&self) {
+        let mut active = self.active.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(stop) = active.take() {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Read position of a log tail within one file
+struct TailCursor {
+    date: String,
+    path: PathBuf,
+    offset: u64,
+    /// Bytes after the last newline, held until the line is complete
+    partial: Vec<u8>,
+}
+
+impl TailCursor {
+    fn new(date: String, path: PathBuf, offset: u64) -> Self {
+        TailCursor { date, path, offset, partial: Vec::new() }
+    }
+
+    /// Complete lines appended since the previous read
+    fn read_new_lines(&mut self) -> Vec<String> {
+        let Ok(mut file) = fs::File::open(&self.path) else { return Vec::new() };
+        let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        if len < self.offset {
+            // Truncated or replaced; start over
+            self.offset = 0;
+            self.partial.clear();
+        }
+        let mut appended = Vec::new();
+        if file.seek(SeekFrom::Start(self.offset)).is_err() || file.read_to_end(&mut appended).is_err() {
+            return Vec::new();
+        }
+        self.offset += appended.len() as u64;
+        self.partial.extend_from_slice(&appended);
+
+        let Some(last_newline) = self.partial.iter().rposition(|&b| b == b'\n') else { return Vec::new() };
+        let rest = self.partial.split_off(last_newline + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        String::from_utf8_lossy(&complete)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The file to follow next: the next rotation of the day, or the first file of a new day
+    fn next_file(&self, logs_dir: &Path, today: &str) -> Option<(String, PathBuf)> {
+        if today != self.date {
+            return Some((today.to_string(), rotated_log_path(logs_dir, today, 0)));
+        }
+        let index = self.path.file_name()?.to_str().and_then(|name| rotation_index(name, &self.date))?;
+        let next = rotated_log_path(logs_dir, &self.date, index + 1);
+        next.exists().then(|| (self.date.clone(), next))
+    }
+}
+
+/// Emit each line written to today's log as a `log-line` event (powers the in-app console)
+/// Starts at the end of the current file, follows rotations and the midnight rollover;
+/// starting a new tail stops the previous one
+#[tauri::command]
+pub fn log_tail_start(app: AppHandle, tail: State<'_, LogTail>) {
+    tail.stop();
+    let stop = Arc::new(AtomicBool::new(false));
+    *tail.active.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::clone(&stop));
+
+    let writer = app.state::<LogWriter>().inner().clone();
+    writer.flush();
+    let logs_dir = get_logs_dir();
+    let today = writer.today();
+    let path = current_log_file(&logs_dir, &today);
+    let offset = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let mut cursor = TailCursor::new(today, path, offset);
+
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::SeqCst) {
+            writer.flush();
+            let mut lines = cursor.read_new_lines();
+            let today = writer.today();
+            if let Some((date, next)) = cursor.next_file(&logs_dir, &today) {
+                // Drain anything written to the old file before switching
+                lines.extend(cursor.read_new_lines());
+                cursor = TailCursor::new(date, next, 0);
+                lines.extend(cursor.read_new_lines());
+            }
+            for line in lines {
+                app.emit("log-line", line).ok();
+            }
+            std::thread::sleep(LOG_TAIL_INTERVAL);
+        }
+    });
+}
+
+/// Stop the running log tail (no-op if none is running)
+#[tauri::command]
+pub fn log_tail_stop(tail: State<'_, LogTail>) {
+    tail.stop();
+}
+
+/// Default number of entries returned by log_query
+const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// Just the fields log_query filters on, borrowed from the raw line
+/// Other fields are skipped by the parser instead of being built into a JSON tree
+#[derive(serde::Deserialize)]
+struct LogLineHeader<'a> {
+    #[serde(borrow)]
+    level: Option<std::borrow::Cow<'a, str>>,
+    #[serde(borrow)]
+    event: Option<std::borrow::Cow<'a, str>>,
+    #[serde(borrow, rename = "traceId")]
+    trace_id: Option<std::borrow::Cow<'a, str>>,
+    #[serde(borrow)]
+    timestamp: Option<std::borrow::Cow<'a, str>>,
+}
+
+/// Filters for log_query; every set field must match
+struct LogFilter {
+    level: Option<String>,
+    event: Option<String>,
+    trace_id: Option<String>,
+    since: Option<chrono::DateTime<chrono::FixedOffset>>,
+}
+
+impl LogFilter {
+    fn matches(&self, header: &LogLineHeader<'_>) -> bool {
+        let field_matches = |wanted: &Option<String>, actual: &Option<std::borrow::Cow<'_, str>>| {
+            wanted.as_deref().is_none_or(|wanted| actual.as_deref() == Some(wanted))
+        };
+        if !field_matches(&self.level, &header.level)
+            || !field_matches(&self.event, &header.event)
+            || !field_matches(&self.trace_id, &header.trace_id)
+        {
+            return false;
+        }
+        match &self.since {
+            Some(since) => header
+                .timestamp
+                .as_deref()
+                .and_then(|timestamp| chrono::DateTime::parse_from_rfc3339(timestamp).ok())
+                .is_some_and(|timestamp| timestamp > *since),
+            None => true,
+        }
+    }
+}
+
+/// Read back recent entries from a daily log file (powers the in-app log viewer)
+/// `date` is YYYY-MM-DD (default: today) and `limit` defaults to 100
+/// `level`, `event` and `trace_id` match exactly and `since` (ISO 8601) keeps entries
+/// stamped after it; all given filters must match
+/// Reads every rotation of the day and returns the newest `limit` matching entries,
+/// newest first; malformed lines are skipped
+#[tauri::command]
+pub fn log_query(
+    writer: State<'_, LogWriter>,
+    date: Option<String>,
+    level: Option<String>,
+    limit: Option<usize>,
+    event: Option<String>,
+    trace_id: Option<String>,
+    since: Option<String>,
+) -> Result<Vec<serde_json::Value>, AppError> {
+    let date = resolve_log_date(&writer, date)?;
+    let since = since
+        .map(|since| {
+            chrono::DateTime::parse_from_rfc3339(&since).map_err(|_| AppError::InvalidArgument {
+                reason: format!("Invalid since timestamp: {} (expected ISO 8601)", since),
+            })
+        })
+        .transpose()?;
+    let filter = LogFilter { level, event, trace_id, since };
+    let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+    if limit == 0 {
+        return Ok(Vec::new());
+    }
+    let logs_dir = get_logs_dir();
+    // Make buffered entries visible to the reader
+    writer.flush();
+
+    // Only the newest `limit` matching lines are kept, and only those are fully parsed
+    let mut recent: VecDeque<String> = VecDeque::with_capacity(limit.min(1024));
+    for log_file in daily_log_files(&logs_dir, &date) {
+        let file = match fs::File::open(&log_file) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(AppError::io("Failed to open log file", e)),
+        };
+
+        for line in BufReader::new(file).lines() {
+            let Ok(line) = line else { continue };
+            let Ok(header) = serde_json::from_str::<LogLineHeader<'_>>(&line) else { continue };
+            if !filter.matches(&header) {
+                continue;
+            }
+            if recent.len() == limit {
+                recent.pop_front();
+            }
+            recent.push_back(line);
+        }
+    }
+
+    Ok(recent
+        .iter()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Bucket for lines that aren't valid JSON or have no `level`
+const UNPARSEABLE_LEVEL: &str = "unparseable";
+
+/// Per-level entry counts for one day
+#[derive(serde::Serialize)]
+pub struct LogSummary {
+    /// Number of non-empty lines across all of the day's files
+    pub total: u64,
+    /// Count per `level` ("info", "warn", ...), plus "unparseable" for malformed lines
+    pub by_level: BTreeMap<String, u64>,
+}
+
+/// Count a day's log entries by level (diagnostics panel health summary)
+/// `date` is YYYY-MM-DD (default: today); a day without logs returns all zeros
+/// Only the level is parsed per line, so this is cheap enough to poll
+#[tauri::command]
+pub fn log_summary(writer: State<'_, LogWriter>, date: Option<String>) -> Result<LogSummary, AppError> {
+    let date = resolve_log_date(&writer, date)?;
+    writer.flush();
+
+    let mut summary = LogSummary { total: 0, by_level: BTreeMap::new() };
+    for log_file in daily_log_files(&get_logs_dir(), &date) {
+        let file = match fs::File::open(&log_file) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(AppError::io("Failed to open log file", e)),
+        };
+
+        for line in BufReader::new(file).split(b'\n') {
+            let Ok(line) = line else { continue };
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            summary.total += 1;
+            let level = serde_json::from_slice::<LogLineHeader<'_>>(&line)
+                .ok()
+                .and_then(|header| header.level.map(|level| level.into_owned()))
+                .unwrap_or_else(|| UNPARSEABLE_LEVEL.to_string());
+            *summary.by_level.entry(level).or_insert(0) += 1;
+        }
+    }
+    Ok(summary)
+}
+
+/// Stream each log file into the zip archive without buffering it in memory
+/// Already-gzipped files are stored as-is rather than compressed again
+fn write_log_zip(zip_path: &Path, log_files: &[PathBuf]) -> zip::result::ZipResult<()> {
+    let mut zip = ZipWriter::new(fs::File::create(zip_path)?);
+    for path in log_files {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let method = if name.ends_with(".gz") {
+            CompressionMethod::Stored
+        } else {
+            CompressionMethod::Deflated
+        };
+        zip.start_file(name, SimpleFileOptions::default().compression_method(method))?;
+        let mut input = fs::File::open(path)?;
+        std::io::copy(&mut input, &mut zip)?;
+    }
+    zip.finish()?.sync_all()?;
+    Ok(())
+}
+
+/// Bundle every log file dated within `from_date..=to_date` into one zip (for support triage)
+/// Plain, rotated and gzipped files are included; an empty range yields a valid empty zip
+/// Returns the path of the archive in the temp directory, ready for a save dialog
+#[tauri::command]
+pub fn export_logs(
+    writer: State<'_, LogWriter>,
+    from_date: String,
+    to_date: String,
+) -> Result<String, AppError> {
+    let (from, to) = parse_log_range(&from_date, &to_date)?;
+    // Include entries still sitting in the write buffer
+    writer.flush();
+    let log_files = log_files_in_range(from, to)?;
+
+    let zip_path =
+        std::env::temp_dir().join(format!("yorutsuke-logs-{}_{}-{}.zip", from, to, uuid::Uuid::new_v4()));
+    if let Err(e) = write_log_zip(&zip_path, &log_files) {
+        fs::remove_file(&zip_path).ok();
+        return Err(AppError::IoError { reason: format!("Failed to write log archive: {}", e) });
+    }
+
+    Ok(zip_path.to_string_lossy().to_string())
+}
+
+/// Validate a `from_date..=to_date` pair of YYYY-MM-DD dates
+fn parse_log_range(from_date: &str, to_date: &str) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let from = parse_log_date(from_date)?;
+    let to = parse_log_date(to_date)?;
+    if from > to {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid log date range: {} is after {}", from, to),
+        });
+    }
+    Ok((from, to))
+}
+
+/// Every log file (plain, rotated or gzipped) dated within `from..=to`, sorted by name
+fn log_files_in_range(from: NaiveDate, to: NaiveDate) -> Result<Vec<PathBuf>, AppError> {
+    let mut log_files: Vec<PathBuf> = match fs::read_dir(get_logs_dir()) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(parse_log_filename)
+                    .is_some_and(|(date, _)| (from..=to).contains(&date))
+            })
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(AppError::io("Failed to read logs directory", e)),
+    };
+    log_files.sort();
+    Ok(log_files)
+}
+
+/// Line stream over one log file (plain or gzipped), in file order
+type LogLines = std::io::Split<BufReader<Box<dyn Read>>>;
+
+fn open_log_lines(path: &Path) -> std::io::Result<LogLines> {
+    let file = fs::File::open(path)?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|extension| extension == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok(BufReader::new(reader).split(b'\n'))
+}
+
+/// Next entry with a valid `timestamp` from `lines`; lines that don't parse are counted in `skipped`
+fn next_timestamped_entry(
+    lines: &mut LogLines,
+    skipped: &mut u64,
+) -> std::io::Result<Option<(DateTime<Utc>, serde_json::Value)>> {
+    for line in lines {
+        let line = line?;
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let entry = serde_json::from_slice::<serde_json::Value>(&line).ok().and_then(|entry| {
+            let timestamp = DateTime::parse_from_rfc3339(entry.get("timestamp")?.as_str()?).ok()?;
+            Some((timestamp.with_timezone(&Utc), entry))
+        });
+        match entry {
+            Some(entry) => return Ok(Some(entry)),
+            None => *skipped += 1,
+        }
+    }
+    Ok(None)
+}
+
+/// k-way merge of `log_files` by timestamp into one compact `.jsonl`
+/// Each file is already in write order, so only one pending entry per file is held in
+/// memory; ties keep file order. Returns `(entry_count, skipped_count)`
+fn write_merged_log(out_path: &Path, log_files: &[PathBuf]) -> std::io::Result<(u64, u64)> {
+    let mut sources = log_files.iter().map(|path| open_log_lines(path)).collect::<std::io::Result<Vec<_>>>()?;
+    let mut pending: Vec<Option<serde_json::Value>> = vec![None; sources.len()];
+    let mut heap = BinaryHeap::with_capacity(sources.len());
+    let mut skipped = 0;
+    for (index, source) in sources.iter_mut().enumerate() {
+        if let Some((timestamp, entry)) = next_timestamped_entry(source, &mut skipped)? {
+            pending[index] = Some(entry);
+            heap.push(Reverse((timestamp, index)));
+        }
+    }
+
+    let mut out = BufWriter::new(fs::File::create(out_path)?);
+    let mut written = 0;
+    while let Some(Reverse((_, index))) = heap.pop() {
+        if let Some(entry) = pending[index].take() {
+            serde_json::to_writer(&mut out, &entry)?;
+            out.write_all(b"\n")?;
+            written += 1;
+        }
+        if let Some((timestamp, entry)) = next_timestamped_entry(&mut sources[index], &mut skipped)? {
+            pending[index] = Some(entry);
+            heap.push(Reverse((timestamp, index)));
+        }
+    }
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    Ok((written, skipped))
+}
+
+/// Result of export_logs_merged
+#[derive(serde::Serialize)]
+pub struct MergedLogExport {
+    /// Combined `.jsonl` in the temp directory
+    pub path: String,
+    pub file_count: u32,
+    pub entry_count: u64,
+    /// Lines skipped because they weren't JSON or had no valid `timestamp`
+    pub skipped_count: u64,
+}
+
+/// Merge every log file dated within `from_date..=to_date` into one `.jsonl` sorted by
+/// `timestamp` (for backend analytics); gzipped and rotated files are included
+/// Streams a k-way merge, so memory stays flat however large the range is
+#[tauri::command]
+pub fn export_logs_merged(
+    writer: State<'_, LogWriter>,
+    from_date: String,
+    to_date: String,
+) -> Result<MergedLogExport, AppError> {
+    let (from, to) = parse_log_range(&from_date, &to_date)?;
+    writer.flush();
+    let log_files = log_files_in_range(from, to)?;
+
+    let out_path =
+        std::env::temp_dir().join(format!("yorutsuke-logs-{}_{}-{}.jsonl", from, to, uuid::Uuid::new_v4()));
+    match write_merged_log(&out_path, &log_files) {
+        Ok((entry_count, skipped_count)) => Ok(MergedLogExport {
+            path: out_path.to_string_lossy().to_string(),
+            file_count: log_files.len() as u32,
+            entry_count,
+            skipped_count,
+        }),
+        Err(e) => {
+            fs::remove_file(&out_path).ok();
+            Err(AppError::IoError { reason: format!("Failed to write merged log export: {}", e) })
+        }
+    }
+}
+
+/// Columns every CSV row has; all other fields go into the trailing `extra` column
+const CSV_COLUMNS: [&str; 5] = ["timestamp", "level", "event", "traceId", "userId"];
+
+/// UTF-8 byte order mark, so Excel doesn't read the file as the local code page
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// A fixed column as cell text: strings as-is, other JSON values serialized, missing empty
+fn csv_cell(value: Option<&serde_json::Value>) -> String {
+    match value {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Write every entry of `log_files` (in file order) as one CSV row
+/// Returns `(row_count, skipped_count)`; lines that aren't JSON objects are skipped
+fn write_log_csv(out_path: &Path, log_files: &[PathBuf]) -> std::io::Result<(u64, u64)> {
+    let mut file = BufWriter::new(fs::File::create(out_path)?);
+    file.write_all(UTF8_BOM)?;
+    let mut csv = csv::Writer::from_writer(file);
+    csv.write_record(CSV_COLUMNS.iter().chain(["extra"].iter()))?;
+
+    let (mut rows, mut skipped) = (0u64, 0u64);
+    for path in log_files {
+        for line in open_log_lines(path)? {
+            let line = line?;
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let Ok(serde_json::Value::Object(mut entry)) = serde_json::from_slice(&line) else {
+                skipped += 1;
+                continue;
+            };
+            let mut record: Vec<String> = CSV_COLUMNS.iter().map(|column| csv_cell(entry.get(*column))).collect();
+            for column in CSV_COLUMNS {
+                entry.remove(column);
+            }
+            record.push(if entry.is_empty() { String::new() } else { serde_json::Value::Object(entry).to_string() });
+            csv.write_record(&record)?;
+            rows += 1;
+        }
+    }
+    csv.into_inner().map_err(|e| e.into_error())?.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    Ok((rows, skipped))
+}
+
+/// Result of export_logs_csv
+#[derive(serde::Serialize)]
+pub struct CsvLogExport {
+    /// CSV in the temp directory
+    pub path: String,
+    pub file_count: u32,
+    pub row_count: u64,
+    /// Lines skipped because they weren't JSON objects
+    pub skipped_count: u64,
+}
+
+/// Flatten every log entry dated within `from_date..=to_date` into a CSV for spreadsheets
+/// Columns are `timestamp, level, event, traceId, userId` plus `extra`, the remaining
+/// fields as one JSON object (empty when there are none), so entries of any shape fit.
+/// UTF-8 with a BOM so Excel shows Japanese text correctly; rows are in file order
+#[tauri::command]
+pub fn export_logs_csv(
+    writer: State<'_, LogWriter>,
+    from_date: String,
+    to_date: String,
+) -> Result<CsvLogExport, AppError> {
+    let (from, to) = parse_log_range(&from_date, &to_date)?;
+    writer.flush();
+    let log_files = log_files_in_range(from, to)?;
+
+    let out_path =
+        std::env::temp_dir().join(format!("yorutsuke-logs-{}_{}-{}.csv", from, to, uuid::Uuid::new_v4()));
+    match write_log_csv(&out_path, &log_files) {
+        Ok((row_count, skipped_count)) => Ok(CsvLogExport {
+            path: out_path.to_string_lossy().to_string(),
+            file_count: log_files.len() as u32,
+            row_count,
+            skipped_count,
+        }),
+        Err(e) => {
+            fs::remove_file(&out_path).ok();
+            Err(AppError::IoError { reason: format!("Failed to write CSV log export: {}", e) })
+        }
+    }
+}
+
+/// Env var selecting the Rust-side log level (error, warn, info, debug, trace; default info)
+const LOG_LEVEL_ENV: &str = "YORUTSUKE_LOG_LEVEL";
+
+/// Trace id used when a command was invoked without one (matches the frontend logger)
+const NO_TRACE: &str = "no-trace";
+
+/// Span/event fields collected as JSON
+#[derive(Default, Clone)]
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl Visit for JsonFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+}
+
+/// Per-span state kept in the registry: its fields and when it opened
+struct SpanRecord {
+    fields: JsonFields,
+    started: Instant,
+}
+
+/// Map tracing levels onto the frontend's level names
+fn level_name(level: &Level) -> &'static str {
+    match *level {
+        Level::ERROR => "error",
+        Level::WARN => "warn",
+        Level::INFO => "info",
+        _ => "debug",
+    }
+}
+
+/// tracing layer writing spans and events as JSONL through the shared LogWriter
+/// Spans log `{name}_start` and `{name}_done` (with `duration_ms`); events inherit the
+/// `trace_id` field of the nearest enclosing span that has one
+struct JsonlLayer {
+    writer: LogWriter,
+}
+
+impl JsonlLayer {
+    /// Trace id of the innermost span in scope that recorded one
+    fn trace_id<'a, S>(scope: impl Iterator<Item = tracing_subscriber::registry::SpanRef<'a, S>>) -> String
+    where
+        S: for<'l> LookupSpan<'l> + 'a,
+    {
+        scope
+            .filter_map(|span| {
+                let extensions = span.extensions();
+                let record = extensions.get::<SpanRecord>()?;
+                record.fields.0.get("trace_id")?.as_str().map(str::to_string)
+            })
+            .next()
+            .unwrap_or_else(|| NO_TRACE.to_string())
+    }
+}
+
+impl<S> Layer<S> for JsonlLayer
+where
+    S: Subscriber + for<'l> LookupSpan<'l>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut fields = JsonFields::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(SpanRecord { fields: fields.clone(), started: Instant::now() });
+
+        let trace_id = Self::trace_id(span.scope());
+        fields.0.remove("trace_id");
+        let level = level_name(span.metadata().level());
+        self.writer.write_event(level, &format!("{}_start", span.name()), &trace_id, fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        if let Some(record) = extensions.get_mut::<SpanRecord>() {
+            values.record(&mut record.fields);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        let level = event.metadata().level();
+        // `#[instrument(err)]` emits a bare `error` field without a message
+        let name = match fields.0.remove("message") {
+            Some(serde_json::Value::String(message)) => message,
+            _ if *level == Level::ERROR => "command_failed".to_string(),
+            _ => event.metadata().name().to_string(),
+        };
+
+        let scope = ctx.event_scope(event);
+        if let Some(span) = ctx.event_span(event) {
+            fields.0.insert("span".to_string(), span.name().into());
+        }
+        let trace_id = scope.map(|scope| Self::trace_id(scope)).unwrap_or_else(|| NO_TRACE.to_string());
+        self.writer.write_event(level_name(level), &name, &trace_id, fields);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let trace_id = Self::trace_id(span.scope());
+        let extensions = span.extensions();
+        let Some(record) = extensions.get::<SpanRecord>() else { return };
+
+        let mut fields = record.fields.clone();
+        fields.0.remove("trace_id");
+        fields.0.insert("duration_ms".to_string(), (record.started.elapsed().as_millis() as u64).into());
+        let level = level_name(span.metadata().level());
+        self.writer.write_event(level, &format!("{}_done", span.name()), &trace_id, fields);
+    }
+}
+
+/// Install the global tracing subscriber, writing through `writer`
+/// Only this crate's spans and events are recorded, at the level named by
+/// YORUTSUKE_LOG_LEVEL (default info); an unparseable value falls back to info
+pub fn init_tracing(writer: LogWriter) {
+    let level = std::env::var(LOG_LEVEL_ENV)
+        .ok()
+        .and_then(|level| level.parse::<LevelFilter>().ok())
+        .unwrap_or(LevelFilter::INFO);
+    let filter = Targets::new().with_target(env!("CARGO_CRATE_NAME"), level);
+
+    tracing_subscriber::registry()
+        .with(JsonlLayer { writer }.with_filter(filter))
+        .try_init()
+        .ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use std::time::Duration;
+
+    #[test]
+    fn rate_limiter_drops_excess_and_summarizes() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(10, start);
+        let allowed = (0..25).filter(|_| bucket.try_acquire(start)).count();
+        assert_eq!(allowed, 10);
+        // Summaries wait for the interval, then report and reset the count
+        assert_eq!(bucket.take_summary(start), None);
+        assert_eq!(bucket.take_summary(start + RATE_SUMMARY_INTERVAL), Some(15));
+        assert_eq!(bucket.take_summary(start + RATE_SUMMARY_INTERVAL * 2), None);
+
+        // Half a second refills half the bucket
+        let later = start + Duration::from_millis(500);
+        let allowed = (0..10).filter(|_| bucket.try_acquire(later)).count();
+        assert_eq!(allowed, 5);
+    }
+
+    #[test]
+    fn log_date_follows_basis_across_midnight() {
+        // 00:30 on 1 March in Tokyo is still 29 February in UTC
+        let now = DateTime::parse_from_rfc3339("2024-02-29T15:30:00Z").unwrap().with_timezone(&Utc);
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        assert_eq!(LogDateBasis::Local.date_at(now, &tokyo), NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(LogDateBasis::Utc.date_at(now, &tokyo), NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+    }
+
+    #[test]
+    fn parses_only_canonical_log_filenames() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        assert_eq!(parse_log_filename("2024-03-01.jsonl"), Some((date, false)));
+        assert_eq!(parse_log_filename("2024-03-01.12.jsonl.gz"), Some((date, true)));
+        assert_eq!(parse_log_filename("2024-3-1.jsonl"), None);
+        assert_eq!(parse_log_filename("2024-03-01x.jsonl"), None);
+        assert_eq!(parse_log_filename("2024-03-01.jsonl.bak"), None);
+        assert_eq!(parse_log_filename("notes.txt"), None);
+    }
+
+    #[test]
+    fn cleanup_cutoff_moves_with_the_log_date() {
+        let dir = std::env::temp_dir().join(format!("yorutsuke-log-cleanup-{}", std::process::id()));
+        let reset = || {
+            fs::remove_dir_all(&dir).ok();
+            fs::create_dir_all(&dir).unwrap();
+            for name in ["2024-02-22.jsonl", "2024-02-23.jsonl", "2024-02-23.1.jsonl", "2024-02-24.jsonl.gz"] {
+                fs::write(dir.join(name), b"{}\n").unwrap();
+            }
+        };
+        let now = DateTime::parse_from_rfc3339("2024-02-29T15:30:00Z").unwrap().with_timezone(&Utc);
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+
+        // Local (Tokyo) today is 1 March: a 7-day retention keeps 23 Feb onwards
+        reset();
+        let result = cleanup_logs_in(&dir, LogDateBasis::Local.date_at(now, &tokyo), 7, None).unwrap();
+        assert_eq!(result.deleted_count, 1);
+        assert_eq!(result.oldest_kept_date.as_deref(), Some("2024-02-23"));
+
+        // UTC today is still 29 February, so 22 Feb is kept for another day
+        reset();
+        let result = cleanup_logs_in(&dir, LogDateBasis::Utc.date_at(now, &tokyo), 7, None).unwrap();
+        assert_eq!(result.deleted_count, 0);
+        assert_eq!(result.oldest_kept_date.as_deref(), Some("2024-02-22"));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn cleanup_expires_rotated_and_gzipped_files() {
+        let dir = std::env::temp_dir().join(format!("yorutsuke-log-variants-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let expired = ["2024-01-09.jsonl", "2024-01-09.1.jsonl", "2024-01-09.jsonl.gz", "2024-01-09.2.jsonl.gz"];
+        let kept = ["2024-02-25.1.jsonl", "2024-02-25.jsonl.gz", "2024-02-29.jsonl", "notes.txt"];
+        for name in expired.iter().chain(&kept) {
+            fs::write(dir.join(name), b"{}\n").unwrap();
+        }
+
+        let today = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let result = cleanup_logs_in(&dir, today, 30, Some(2)).unwrap();
+        assert_eq!(result.deleted_count, 4);
+        assert_eq!(result.compressed_count, 1);
+        assert_eq!(result.oldest_kept_date.as_deref(), Some("2024-02-25"));
+        for name in expired {
+            assert!(!dir.join(name).exists(), "{} should be expired", name);
+        }
+        // The old rotation is compressed alongside the already gzipped base file
+        assert!(dir.join("2024-02-25.1.jsonl.gz").exists());
+        assert!(dir.join("2024-02-25.jsonl.gz").exists());
+        assert!(dir.join("2024-02-29.jsonl").exists());
+        assert!(dir.join("notes.txt").exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
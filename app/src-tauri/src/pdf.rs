@@ -0,0 +1,212 @@
+//! PDF export of stored receipts (one receipt per A4 page) for expense reports
+
+use std::fs;
+use std::io::{BufWriter, Cursor};
+
+use image::codecs::jpeg::{JpegDecoder, JpegEncoder};
+use image::{ColorType, ImageDecoder};
+use printpdf::{
+    BuiltinFont, ColorBits, ColorSpace, Image, ImageFilter, ImageTransform, ImageXObject, IndirectFontRef, Mm,
+    PdfDocument, PdfLayerReference, Px,
+};
+
+use crate::error::AppError;
+use crate::storage::{find_stored_image, validate_image_id};
+use crate::task::run_blocking;
+
+/// A4 portrait
+const PAGE_WIDTH_MM: f32 = 210.0;
+const PAGE_HEIGHT_MM: f32 = 297.0;
+const MARGIN_MM: f32 = 15.0;
+
+/// Space kept under the image for its caption
+const CAPTION_HEIGHT_MM: f32 = 10.0;
+const CAPTION_FONT_SIZE: f32 = 9.0;
+const TITLE_FONT_SIZE: f32 = 20.0;
+const NOTE_FONT_SIZE: f32 = 10.0;
+
+/// Resolution images are placed at before scaling to fit the page
+const IMAGE_DPI: f32 = 300.0;
+
+/// Most receipts in one PDF (the JPEGs are held in memory until the file is written)
+const MAX_PDF_IMAGES: usize = 500;
+
+/// Quality non-JPEG stored images (WebP/PNG) are converted at for embedding
+const PDF_JPEG_QUALITY: u8 = 85;
+
+/// A written PDF export
+#[derive(serde::Serialize)]
+pub struct PdfExport {
+    /// PDF in the temp directory
+    pub path: String,
+    /// Receipt pages (the cover page not counted)
+    pub page_count: u32,
+    /// Ids skipped because no readable stored image exists (also listed on the cover)
+    pub missing_ids: Vec<String>,
+}
+
+/// A stored image ready to embed: JPEG bytes plus what the PDF needs to know about them
+struct PdfImage {
+    jpeg: Vec<u8>,
+    width: u32,
+    height: u32,
+    grayscale: bool,
+    /// Caption date (stored file's modification date)
+    date: String,
+}
+
+/// Load `image_id` as embeddable JPEG (stored JPEGs are used as-is, others converted)
+/// None when the image is missing, encrypted or undecodable
+fn load_pdf_image(image_id: &str) -> Result<Option<PdfImage>, AppError> {
+    let Some(path) = find_stored_image(image_id)? else { return Ok(None) };
+    let Ok(bytes) = fs::read(&path) else { return Ok(None) };
+    let date = fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .map(|time| chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+
+    let jpeg = if image::guess_format(&bytes).ok() == Some(image::ImageFormat::Jpeg) {
+        bytes
+    } else {
+        let Ok(img) = image::load_from_memory(&bytes) else { return Ok(None) };
+        let mut out = Vec::new();
+        let img = if img.color().has_color() {
+            image::DynamicImage::ImageRgb8(img.to_rgb8())
+        } else {
+            image::DynamicImage::ImageLuma8(img.to_luma8())
+        };
+        JpegEncoder::new_with_quality(&mut out, PDF_JPEG_QUALITY)
+            .encode_image(&img)
+            .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to convert image for PDF: {}", e) })?;
+        out
+    };
+
+    // Only RGB and grayscale JPEGs embed directly as DCT streams
+    let Ok(decoder) = JpegDecoder::new(Cursor::new(&jpeg)) else { return Ok(None) };
+    let (width, height) = decoder.dimensions();
+    let grayscale = match decoder.color_type() {
+        ColorType::L8 => true,
+        ColorType::Rgb8 => false,
+        _ => return Ok(None),
+    };
+    Ok(Some(PdfImage { jpeg, width, height, grayscale, date }))
+}
+
+/// The built-in PDF fonts only cover Latin text; anything else is shown as '?'
+fn pdf_text(text: &str) -> String {
+    text.chars().map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '?' }).collect()
+}
+
+/// Place `image` scaled to fit above the caption area, centered horizontally
+fn draw_image_page(layer: PdfLayerReference, font: &IndirectFontRef, image_id: &str, image: PdfImage) {
+    let available_width = PAGE_WIDTH_MM - 2.0 * MARGIN_MM;
+    let available_height = PAGE_HEIGHT_MM - 2.0 * MARGIN_MM - CAPTION_HEIGHT_MM;
+    let natural_width = image.width as f32 / IMAGE_DPI * 25.4;
+    let natural_height = image.height as f32 / IMAGE_DPI * 25.4;
+    let scale = (available_width / natural_width).min(available_height / natural_height);
+    let (drawn_width, drawn_height) = (natural_width * scale, natural_height * scale);
+
+    let x = (PAGE_WIDTH_MM - drawn_width) / 2.0;
+    let y = MARGIN_MM + CAPTION_HEIGHT_MM + (available_height - drawn_height);
+    layer.use_text(
+        pdf_text(&format!("{}  {}", image_id, image.date)),
+        CAPTION_FONT_SIZE,
+        Mm(x),
+        Mm(y - CAPTION_HEIGHT_MM / 2.0 - 1.0),
+        font,
+    );
+
+    let xobject = ImageXObject {
+        width: Px(image.width as usize),
+        height: Px(image.height as usize),
+        color_space: if image.grayscale { ColorSpace::Greyscale } else { ColorSpace::Rgb },
+        bits_per_component: ColorBits::Bit8,
+        interpolate: true,
+        image_data: image.jpeg,
+        image_filter: Some(ImageFilter::DCT),
+        smask: None,
+        clipping_bbox: None,
+    };
+    Image::from(xobject).add_to_layer(
+        layer,
+        ImageTransform {
+            translate_x: Some(Mm(x)),
+            translate_y: Some(Mm(y)),
+            scale_x: Some(scale),
+            scale_y: Some(scale),
+            dpi: Some(IMAGE_DPI),
+            ..Default::default()
+        },
+    );
+}
+
+/// Bundle stored receipts into one PDF: a cover page with `title`, then one receipt
+/// per A4 page (scaled to fit within 15mm margins) captioned with its id and date
+/// Missing, encrypted or unreadable images are skipped and listed on the cover; invalid
+/// ids fail the call. At most 500 images. The cover uses a built-in font, so non-Latin
+/// characters in the title are shown as '?' there (the document title keeps them)
+#[tauri::command]
+pub async fn export_images_pdf(image_ids: Vec<String>, title: String) -> Result<PdfExport, AppError> {
+    if image_ids.is_empty() {
+        return Err(AppError::InvalidArgument { reason: "No images to export".to_string() });
+    }
+    if image_ids.len() > MAX_PDF_IMAGES {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Too many images for one PDF: {} (max {})", image_ids.len(), MAX_PDF_IMAGES),
+        });
+    }
+    for id in &image_ids {
+        validate_image_id(id)?;
+    }
+
+    run_blocking(move || {
+        let (doc, cover_page, cover_layer) =
+            PdfDocument::new(title.as_str(), Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), "cover");
+        let font = doc
+            .add_builtin_font(BuiltinFont::Helvetica)
+            .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to load PDF font: {}", e) })?;
+
+        let mut page_count = 0u32;
+        let mut missing_ids = Vec::new();
+        for id in &image_ids {
+            let Some(image) = load_pdf_image(id)? else {
+                missing_ids.push(id.clone());
+                continue;
+            };
+            let (page, layer) = doc.add_page(Mm(PAGE_WIDTH_MM), Mm(PAGE_HEIGHT_MM), "receipt");
+            draw_image_page(doc.get_page(page).get_layer(layer), &font, id, image);
+            page_count += 1;
+        }
+
+        let cover = doc.get_page(cover_page).get_layer(cover_layer);
+        let mut y = PAGE_HEIGHT_MM - 3.0 * MARGIN_MM;
+        cover.use_text(pdf_text(&title), TITLE_FONT_SIZE, Mm(MARGIN_MM), Mm(y), &font);
+        y -= 12.0;
+        let summary = format!(
+            "{} receipt(s), exported {}",
+            page_count,
+            chrono::Local::now().format("%Y-%m-%d")
+        );
+        cover.use_text(summary, NOTE_FONT_SIZE, Mm(MARGIN_MM), Mm(y), &font);
+        if !missing_ids.is_empty() {
+            y -= 8.0;
+            let note = format!("Skipped {} missing image(s):", missing_ids.len());
+            cover.use_text(note, NOTE_FONT_SIZE, Mm(MARGIN_MM), Mm(y), &font);
+            for id in &missing_ids {
+                y -= 5.0;
+                if y < MARGIN_MM {
+                    break;
+                }
+                cover.use_text(id.as_str(), NOTE_FONT_SIZE, Mm(MARGIN_MM + 5.0), Mm(y), &font);
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("yorutsuke-receipts-{}.pdf", uuid::Uuid::new_v4()));
+        let file = fs::File::create(&path).map_err(|e| AppError::io_at(&path, "Failed to create PDF", e))?;
+        doc.save(&mut BufWriter::new(file))
+            .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to write PDF: {}", e) })?;
+
+        Ok(PdfExport { path: path.to_string_lossy().to_string(), page_count, missing_ids })
+    })
+    .await
+}
@@ -0,0 +1,230 @@
+//! Lightweight image inspection (header reads only, no full decode)
+
+use std::fs;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use image::{ImageDecoder, ImageFormat, ImageReader};
+
+use crate::error::AppError;
+
+/// Bytes read from the start of a file for magic-byte sniffing
+const SNIFF_BYTES: usize = 256;
+
+/// Formats the compression pipeline accepts as input
+const ACCEPTED_FORMATS: &[ImageFormat] = &[
+    ImageFormat::Jpeg,
+    ImageFormat::Png,
+    ImageFormat::WebP,
+    ImageFormat::Gif,
+    ImageFormat::Bmp,
+    ImageFormat::Tiff,
+];
+
+/// Stable lowercase name for a detected format (used in results and errors)
+pub fn format_name(format: ImageFormat) -> String {
+    match format {
+        ImageFormat::Jpeg => "jpeg".to_string(),
+        ImageFormat::Png => "png".to_string(),
+        ImageFormat::WebP => "webp".to_string(),
+        ImageFormat::Gif => "gif".to_string(),
+        ImageFormat::Bmp => "bmp".to_string(),
+        ImageFormat::Tiff => "tiff".to_string(),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+/// Source formats the compression pipeline can decode
+#[derive(Clone, Copy)]
+pub enum SourceFormat {
+    Image(ImageFormat),
+    /// HEIC/HEIF (iPhone photos), decoded through libheif's image-crate hooks
+    #[cfg(feature = "heic")]
+    Heif,
+}
+
+impl SourceFormat {
+    pub fn name(self) -> String {
+        match self {
+            SourceFormat::Image(format) => format_name(format),
+            #[cfg(feature = "heic")]
+            SourceFormat::Heif => "heif".to_string(),
+        }
+    }
+
+    /// Conventional file extension for this format ("jpg", "png", "heic", ...)
+    pub fn extension(self) -> &'static str {
+        match self {
+            SourceFormat::Image(format) => format.extensions_str().first().copied().unwrap_or("img"),
+            #[cfg(feature = "heic")]
+            SourceFormat::Heif => "heic",
+        }
+    }
+
+    /// Create an image reader for `path` that decodes as this format
+    pub fn reader(self, path: &Path) -> Result<ImageReader<BufReader<fs::File>>, AppError> {
+        let file = fs::File::open(path)
+            .map_err(|e| AppError::io_at(path, "Failed to open image", e))?;
+        let reader = BufReader::new(file);
+        match self {
+            SourceFormat::Image(format) => Ok(ImageReader::with_format(reader, format)),
+            // libheif registers HEIF format detection hooks, so content guessing finds it
+            #[cfg(feature = "heic")]
+            SourceFormat::Heif => ImageReader::new(reader)
+                .with_guessed_format()
+                .map_err(|e| AppError::io("Failed to read image", e)),
+        }
+    }
+}
+
+/// Register optional decoders with the image crate (call once at startup)
+pub fn register_decoders() {
+    #[cfg(feature = "heic")]
+    libheif_rs::integration::image::register_all_decoding_hooks();
+}
+
+/// Detect the image format from the file's magic bytes (the extension is ignored)
+/// Anything outside ACCEPTED_FORMATS is an `UnsupportedFormat` error naming what
+/// the file actually is (e.g. "pdf", "heif") when that can be determined;
+/// HEIF is accepted when built with the `heic` feature
+pub fn sniff_format(path: &Path) -> Result<SourceFormat, AppError> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| AppError::io_at(path, "Failed to open file", e))?;
+    let mut header = Vec::with_capacity(SNIFF_BYTES);
+    file.by_ref()
+        .take(SNIFF_BYTES as u64)
+        .read_to_end(&mut header)
+        .map_err(|e| AppError::io("Failed to read file", e))?;
+
+    #[cfg(feature = "heic")]
+    if infer::image::is_heif(&header) {
+        return Ok(SourceFormat::Heif);
+    }
+
+    match image::guess_format(&header) {
+        Ok(format) if ACCEPTED_FORMATS.contains(&format) => Ok(SourceFormat::Image(format)),
+        Ok(format) => Err(AppError::UnsupportedFormat { format: format_name(format) }),
+        Err(_) => Err(AppError::UnsupportedFormat {
+            format: infer::get(&header)
+                .map(|kind| kind.extension().to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+        }),
+    }
+}
+
+/// Image metadata returned by probe_image
+#[derive(serde::Serialize)]
+pub struct ImageProbe {
+    pub width: u32,
+    pub height: u32,
+    pub format: String,
+    pub file_size: u64,
+    pub has_exif: bool,
+}
+
+/// Read an image's dimensions, format and EXIF presence without decoding pixels
+/// Lets the upload preview decide what to do before paying for a full compress
+#[tauri::command]
+pub fn probe_image(input_path: String) -> Result<ImageProbe, AppError> {
+    let path = Path::new(&input_path);
+    let format = sniff_format(path)?;
+    let file_size = fs::metadata(path)
+        .map_err(|e| AppError::io("Failed to read metadata", e))?
+        .len();
+
+    let mut decoder = format
+        .reader(path)?
+        .into_decoder()
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to read image header: {}", e) })?;
+    let (width, height) = decoder.dimensions();
+    let has_exif = matches!(decoder.exif_metadata(), Ok(Some(exif)) if !exif.is_empty());
+
+    Ok(ImageProbe {
+        width,
+        height,
+        format: format.name(),
+        file_size,
+        has_exif,
+    })
+}
+
+/// EXIF date-time layout (`YYYY:MM:DD HH:MM:SS`)
+const EXIF_DATETIME_FORMAT: &str = "%Y:%m:%d %H:%M:%S";
+
+/// First ASCII string of an EXIF field, trimmed of padding
+fn exif_ascii(exif: &exif::Exif, tag: exif::Tag) -> Option<String> {
+    match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => {
+            let text = String::from_utf8_lossy(values.first()?);
+            Some(text.trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string())
+        }
+        _ => None,
+    }
+}
+
+/// When the photo was taken, from EXIF `DateTimeOriginal` (falling back to `DateTime`)
+/// Returned as ISO 8601, with the UTC offset when EXIF records one (`OffsetTimeOriginal`),
+/// otherwise as camera-local time without an offset
+/// Missing or malformed EXIF yields None so the caller can fall back to today
+#[tauri::command]
+pub fn get_image_captured_at(input_path: String) -> Result<Option<String>, AppError> {
+    let path = Path::new(&input_path);
+    let file = fs::File::open(path).map_err(|e| AppError::io_at(path, "Failed to open image", e))?;
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut BufReader::new(file)) else {
+        return Ok(None);
+    };
+
+    let (raw, offset_tag) = match exif_ascii(&exif, exif::Tag::DateTimeOriginal) {
+        Some(raw) => (raw, exif::Tag::OffsetTimeOriginal),
+        None => match exif_ascii(&exif, exif::Tag::DateTime) {
+            Some(raw) => (raw, exif::Tag::OffsetTime),
+            None => return Ok(None),
+        },
+    };
+    let Ok(captured) = chrono::NaiveDateTime::parse_from_str(&raw, EXIF_DATETIME_FORMAT) else {
+        return Ok(None);
+    };
+
+    let local = captured.format("%Y-%m-%dT%H:%M:%S").to_string();
+    let offset = exif_ascii(&exif, offset_tag)
+        .and_then(|offset| chrono::DateTime::parse_from_rfc3339(&format!("{}{}", local, offset)).ok());
+    Ok(Some(match offset {
+        Some(with_offset) => with_offset.to_rfc3339(),
+        None => local,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 64x64 HEVC still image (license in fixtures/)
+    const TINY_HEIC: &[u8] = include_bytes!("../fixtures/tiny.heic");
+
+    fn write_fixture(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("yorutsuke-{}-{}.heic", name, std::process::id()));
+        fs::write(&path, TINY_HEIC).unwrap();
+        path
+    }
+
+    #[cfg(feature = "heic")]
+    #[test]
+    fn heic_fixture_decodes() {
+        register_decoders();
+        let path = write_fixture("heic-decode");
+        let format = sniff_format(&path).unwrap();
+        assert!(matches!(format, SourceFormat::Heif));
+        let img = format.reader(&path).unwrap().decode().unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!((img.width(), img.height()), (64, 64));
+    }
+
+    #[cfg(not(feature = "heic"))]
+    #[test]
+    fn heic_is_unsupported_without_the_feature() {
+        let path = write_fixture("heic-unsupported");
+        let result = sniff_format(&path);
+        fs::remove_file(&path).ok();
+        assert!(matches!(&result, Err(AppError::UnsupportedFormat { format }) if format == "heif"));
+    }
+}
@@ -0,0 +1,61 @@
+//! Persisted compression queue (~/.yorutsuke/queue.json)
+//! Lets the frontend resume pending compressions after the app is killed mid-batch
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::AppError;
+use crate::storage::{get_app_home_dir, write_atomic};
+
+/// Current queue file schema; bump when the item shape changes
+const QUEUE_SCHEMA_VERSION: u32 = 1;
+
+/// One queued compression
+/// `status` is owned by the frontend (e.g. "pending", "compressed") and stored verbatim
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct QueueItem {
+    pub input_path: String,
+    pub image_id: String,
+    pub status: String,
+}
+
+/// On-disk queue file
+#[derive(serde::Serialize, serde::Deserialize)]
+struct QueueFile {
+    version: u32,
+    items: Vec<QueueItem>,
+}
+
+pub fn queue_path() -> PathBuf {
+    get_app_home_dir().join("queue.json")
+}
+
+/// Save the pending compression queue, replacing any previous one atomically
+/// A crash mid-save leaves the previous queue intact rather than a truncated file
+#[tauri::command]
+pub fn persist_compress_queue(items: Vec<QueueItem>) -> Result<(), AppError> {
+    let queue = QueueFile { version: QUEUE_SCHEMA_VERSION, items };
+    let json = serde_json::to_vec_pretty(&queue)
+        .map_err(|e| AppError::InvalidArgument { reason: format!("Failed to serialize queue: {}", e) })?;
+    write_atomic(&queue_path(), &json)
+        .map_err(|e| AppError::io("Failed to save compress queue", e))
+}
+
+/// Load the saved compression queue (empty if none was saved)
+/// A file written by a newer schema version is rejected rather than misread
+#[tauri::command]
+pub fn load_compress_queue() -> Result<Vec<QueueItem>, AppError> {
+    let json = match fs::read(queue_path()) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::io("Failed to read compress queue", e)),
+    };
+    let queue: QueueFile = serde_json::from_slice(&json)
+        .map_err(|e| AppError::DecodeFailed { reason: format!("Invalid compress queue file: {}", e) })?;
+    if queue.version > QUEUE_SCHEMA_VERSION {
+        return Err(AppError::UnsupportedFormat {
+            format: format!("compress queue schema v{}", queue.version),
+        });
+    }
+    Ok(queue.items)
+}
@@ -0,0 +1,132 @@
+//! Local ledger of uploaded bytes, so the monthly image quota the backend enforces can
+//! be checked before an upload is attempted (~/.yorutsuke/quota.json)
+//! The backend stays authoritative; this only saves uploads that would be rejected
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Datelike, Local, TimeZone, Utc};
+
+use crate::error::AppError;
+use crate::storage::{get_app_home_dir, write_atomic};
+
+/// Serializes read-modify-write of the ledger between concurrent commands
+static LEDGER_LOCK: Mutex<()> = Mutex::new(());
+
+/// One recorded upload
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct UploadRecord {
+    bytes: u64,
+    timestamp: DateTime<Utc>,
+}
+
+/// Contents of quota.json
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct QuotaLedger {
+    uploads: Vec<UploadRecord>,
+}
+
+pub(crate) fn ledger_path() -> PathBuf {
+    get_app_home_dir().join("quota.json")
+}
+
+/// Load the ledger; a missing or damaged file starts an empty one (the backend still
+/// enforces the real quota, so losing local history only weakens the pre-check)
+fn read_ledger() -> QuotaLedger {
+    let Ok(json) = fs::read(ledger_path()) else { return QuotaLedger::default() };
+    serde_json::from_slice(&json).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "quota_ledger_invalid_reset");
+        QuotaLedger::default()
+    })
+}
+
+fn write_ledger(ledger: &QuotaLedger) -> Result<(), AppError> {
+    let json = serde_json::to_vec(ledger)
+        .map_err(|e| AppError::InvalidArgument { reason: format!("Failed to serialize quota ledger: {}", e) })?;
+    write_atomic(&ledger_path(), &json).map_err(|e| AppError::io("Failed to save quota ledger", e))
+}
+
+/// Whether `timestamp` falls in the same calendar month as `now`, in `now`'s timezone
+fn same_month<Tz: TimeZone>(timestamp: &DateTime<Utc>, now: &DateTime<Tz>) -> bool {
+    let local = timestamp.with_timezone(&now.timezone());
+    local.year() == now.year() && local.month() == now.month()
+}
+
+/// Bytes uploaded in the calendar month of `now`
+fn used_in_month<Tz: TimeZone>(ledger: &QuotaLedger, now: &DateTime<Tz>) -> u64 {
+    ledger.uploads.iter().filter(|u| same_month(&u.timestamp, now)).map(|u| u.bytes).sum()
+}
+
+/// Record a successful upload of `bytes` at `timestamp` (RFC 3339, default now)
+/// Records from before the current month are dropped while saving, so the file stays small
+#[tauri::command]
+pub fn record_upload(bytes: u64, timestamp: Option<String>) -> Result<(), AppError> {
+    let timestamp = match timestamp {
+        Some(text) => DateTime::parse_from_rfc3339(&text)
+            .map_err(|e| AppError::InvalidArgument { reason: format!("Invalid timestamp: {} ({})", text, e) })?
+            .with_timezone(&Utc),
+        None => Utc::now(),
+    };
+
+    let _guard = LEDGER_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut ledger = read_ledger();
+    let now = Local::now();
+    ledger.uploads.retain(|u| same_month(&u.timestamp, &now));
+    ledger.uploads.push(UploadRecord { bytes, timestamp });
+    write_ledger(&ledger)
+}
+
+/// Result of check_quota
+#[derive(serde::Serialize)]
+pub struct QuotaStatus {
+    pub used_this_month: u64,
+    /// `limit_bytes - used_this_month`, 0 once the limit is reached
+    pub remaining: u64,
+    pub over_limit: bool,
+}
+
+/// Bytes uploaded this month against `limit_bytes` (the backend's monthly quota)
+/// Months follow the user's local timezone, so usage resets at local midnight on the 1st
+/// `over_limit` is true once usage reaches the limit
+#[tauri::command]
+pub fn check_quota(limit_bytes: u64) -> QuotaStatus {
+    let ledger = {
+        let _guard = LEDGER_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        read_ledger()
+    };
+    let used_this_month = used_in_month(&ledger, &Local::now());
+    QuotaStatus {
+        used_this_month,
+        remaining: limit_bytes.saturating_sub(used_this_month),
+        over_limit: used_this_month >= limit_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn record(bytes: u64, timestamp: &str) -> UploadRecord {
+        UploadRecord { bytes, timestamp: DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc) }
+    }
+
+    #[test]
+    fn month_boundary_follows_local_timezone() {
+        let ledger = QuotaLedger {
+            uploads: vec![
+                // 2024-02-29 23:30 in Tokyo: still February there
+                record(100, "2024-02-29T14:30:00Z"),
+                // 2024-03-01 00:30 in Tokyo, though still February in UTC
+                record(20, "2024-02-29T15:30:00Z"),
+                record(3, "2024-03-15T00:00:00Z"),
+            ],
+        };
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let march = tokyo.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+        assert_eq!(used_in_month(&ledger, &march), 23);
+        let february = tokyo.with_ymd_and_hms(2024, 2, 10, 12, 0, 0).unwrap();
+        assert_eq!(used_in_month(&ledger, &february), 100);
+    }
+}
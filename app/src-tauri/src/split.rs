@@ -0,0 +1,146 @@
+//! Splitting one photo of several receipts laid out on a table into one image each
+//! Best effort: receipts that touch or overlap come out as one region, and the UI
+//! lets the user discard bad splits
+
+use std::path::Path;
+
+use image::imageops::FilterType;
+use image::{GrayImage, Luma};
+use imageproc::region_labelling::{connected_components, Connectivity};
+
+use crate::analysis::otsu_threshold;
+use crate::compress::{compress_decoded, open_oriented, CompressArgs, CompressResult, CropRect};
+use crate::error::AppError;
+use crate::probe::sniff_format;
+use crate::task::run_blocking;
+
+/// Longest side regions are detected at; crops are then taken from the full image
+const SPLIT_DETECT_DIMENSION: u32 = 800;
+
+/// Default smallest region kept, as a fraction of the image area
+const DEFAULT_MIN_AREA_RATIO: f64 = 0.02;
+
+/// Regions covering less of their bounding box than this aren't paper-shaped
+/// (a tilted receipt still fills about half of its box)
+const MIN_FILL_RATIO: f64 = 0.4;
+
+/// Most receipts returned from one photo
+const MAX_REGIONS: usize = 20;
+
+/// One receipt cut out of the photo and stored under a new id
+#[derive(serde::Serialize)]
+pub struct SplitReceipt {
+    /// Region in upright source pixels
+    pub bounds: CropRect,
+    pub result: CompressResult,
+}
+
+/// Bright paper against a darker table: 255 (paper) / 0 (background) at Otsu's threshold
+fn paper_mask(gray: &GrayImage) -> GrayImage {
+    let threshold = otsu_threshold(gray);
+    GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+        Luma([if gray.get_pixel(x, y).0[0] > threshold { 255 } else { 0 }])
+    })
+}
+
+/// Bounding boxes of paper-shaped bright regions, largest first, in `mask` pixels
+fn find_regions(mask: &GrayImage, min_area_ratio: f64) -> Vec<CropRect> {
+    let labels = connected_components(mask, Connectivity::Eight, Luma([0u8]));
+    // Per label: pixel count, min x, min y, max x, max y
+    let mut stats: Vec<(u64, u32, u32, u32, u32)> = Vec::new();
+    for (x, y, label) in labels.enumerate_pixels() {
+        let label = label.0[0] as usize;
+        if label == 0 {
+            continue;
+        }
+        if stats.len() < label {
+            stats.resize(label, (0, u32::MAX, u32::MAX, 0, 0));
+        }
+        let entry = &mut stats[label - 1];
+        entry.0 += 1;
+        entry.1 = entry.1.min(x);
+        entry.2 = entry.2.min(y);
+        entry.3 = entry.3.max(x);
+        entry.4 = entry.4.max(y);
+    }
+
+    let min_area = (f64::from(mask.width()) * f64::from(mask.height()) * min_area_ratio) as u64;
+    let mut regions: Vec<(u64, CropRect)> = stats
+        .into_iter()
+        .filter_map(|(count, min_x, min_y, max_x, max_y)| {
+            let rect = CropRect { x: min_x, y: min_y, width: max_x - min_x + 1, height: max_y - min_y + 1 };
+            let box_area = u64::from(rect.width) * u64::from(rect.height);
+            (count >= min_area && count as f64 >= box_area as f64 * MIN_FILL_RATIO).then_some((count, rect))
+        })
+        .collect();
+    regions.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+    regions.truncate(MAX_REGIONS);
+    regions.into_iter().map(|(_, rect)| rect).collect()
+}
+
+/// Map a detection-scale rectangle onto the full-size image, padded by 1%
+fn to_source_rect(rect: CropRect, scale: f64, width: u32, height: u32) -> CropRect {
+    let pad = (f64::from(width.max(height)) * 0.01) as u32;
+    let x = ((f64::from(rect.x) * scale) as u32).saturating_sub(pad);
+    let y = ((f64::from(rect.y) * scale) as u32).saturating_sub(pad);
+    let right = ((f64::from(rect.x + rect.width) * scale).ceil() as u32 + pad).min(width);
+    let bottom = ((f64::from(rect.y + rect.height) * scale).ceil() as u32 + pad).min(height);
+    CropRect { x, y, width: right - x, height: bottom - y }
+}
+
+/// Detect separate receipts in one photo and store each as its own image
+/// Regions are bright connected areas (paper on a darker surface) of at least
+/// `min_area_ratio` of the photo (default 0.02); each crop goes through the normal
+/// compress pipeline with `options` under a fresh id. When nothing or only one
+/// region is found, a single image is returned (the detected region, or the whole
+/// photo). Results are ordered largest region first, at most 20
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(trace_id = trace_id.as_deref().unwrap_or("no-trace")), err(Display))]
+pub async fn split_receipts(
+    input_path: String,
+    min_area_ratio: Option<f64>,
+    options: Option<CompressArgs>,
+    trace_id: Option<String>,
+) -> Result<Vec<SplitReceipt>, AppError> {
+    let min_area_ratio = min_area_ratio.unwrap_or(DEFAULT_MIN_AREA_RATIO);
+    if !(0.0..1.0).contains(&min_area_ratio) {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid min_area_ratio: {} (expected 0-1)", min_area_ratio),
+        });
+    }
+    run_blocking(move || {
+        let path = Path::new(&input_path);
+        if !path.exists() {
+            return Err(AppError::FileNotFound { path: input_path.clone() });
+        }
+        let img = open_oriented(path, sniff_format(path)?)?;
+        let (width, height) = (img.width(), img.height());
+
+        let small = if width.max(height) > SPLIT_DETECT_DIMENSION {
+            img.resize(SPLIT_DETECT_DIMENSION, SPLIT_DETECT_DIMENSION, FilterType::Triangle)
+        } else {
+            img.clone()
+        };
+        let scale = f64::from(width) / f64::from(small.width());
+        let mut regions: Vec<CropRect> = find_regions(&paper_mask(&small.to_luma8()), min_area_ratio)
+            .into_iter()
+            .map(|rect| to_source_rect(rect, scale, width, height))
+            .collect();
+        if regions.is_empty() {
+            regions.push(CropRect { x: 0, y: 0, width, height });
+        }
+        tracing::info!(regions = regions.len(), "split_receipts_detected");
+
+        let crops: Vec<_> = regions
+            .iter()
+            .map(|r| (img.crop_imm(r.x, r.y, r.width, r.height), uuid::Uuid::new_v4().to_string()))
+            .collect();
+        let results = compress_decoded(&crops, &input_path, options)?;
+        Ok(regions
+            .into_iter()
+            .zip(results)
+            .map(|(bounds, result)| SplitReceipt { bounds, result })
+            .collect())
+    })
+    .await
+}
@@ -0,0 +1,164 @@
+//! Text stamp (transaction id, date) burned into a stored receipt for archival
+//! Opt-in and destructive: the stamped JPEG replaces `{id}.jpg`, and only re-uploading
+//! the original brings back an unstamped copy
+
+use std::fs;
+
+use ab_glyph::{Font, FontRef, PxScale};
+use image::codecs::jpeg::JpegEncoder;
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+
+use crate::compress::parse_quality;
+use crate::error::AppError;
+use crate::storage::{get_data_dir, validate_image_id, write_atomic};
+use crate::task::run_blocking;
+
+/// Bundled so stamps look the same on every platform (license in assets/fonts)
+const STAMP_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono.ttf");
+
+/// Longest accepted stamp text, in characters
+const MAX_STAMP_CHARS: usize = 100;
+
+/// Font size as a fraction of the shorter image side, clamped to a readable range
+const STAMP_SIZE_RATIO: f32 = 0.035;
+const MIN_STAMP_PX: f32 = 12.0;
+const MAX_STAMP_PX: f32 = 64.0;
+
+/// The stamp (box included) never spans more than this fraction of the image width
+const MAX_STAMP_WIDTH_RATIO: f32 = 0.9;
+
+/// Opacity of the white box behind the text
+const BOX_ALPHA: f32 = 0.7;
+
+const TEXT_COLOR: Rgb<u8> = Rgb([0, 0, 0]);
+
+/// A stamped image, as now stored
+#[derive(serde::Serialize)]
+pub struct StampResult {
+    /// MD5 of the new `{id}.jpg`
+    pub md5: String,
+    pub width: u32,
+    pub height: u32,
+    pub compressed_size: u64,
+    /// Font size used, in pixels
+    pub font_size: f32,
+}
+
+/// The bundled monospace font (also used to render total_region's label templates)
+pub fn load_font() -> Result<FontRef<'static>, AppError> {
+    FontRef::try_from_slice(STAMP_FONT)
+        .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to load stamp font: {}", e) })
+}
+
+/// Reject empty/overlong text and characters the bundled font can't draw
+/// (which would silently render as boxes)
+fn validate_stamp_text(font: &FontRef<'_>, text: &str) -> Result<(), AppError> {
+    if text.trim().is_empty() {
+        return Err(AppError::InvalidArgument { reason: "Stamp text must not be empty".to_string() });
+    }
+    if text.chars().count() > MAX_STAMP_CHARS {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Stamp text too long (max {} characters)", MAX_STAMP_CHARS),
+        });
+    }
+    if let Some(c) = text.chars().find(|&c| c.is_control() || font.glyph_id(c).0 == 0) {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Stamp text contains an unsupported character: {:?}", c),
+        });
+    }
+    Ok(())
+}
+
+/// Draw `text` in the bottom-right corner on a translucent white box
+/// The corner keeps the stamp clear of the items and total in the middle of the
+/// receipt; the size scales with the image and shrinks for long text
+fn draw_stamp(img: &mut RgbImage, font: &FontRef<'_>, text: &str) -> Result<f32, AppError> {
+    let (width, height) = img.dimensions();
+    let short_side = width.min(height) as f32;
+    let margin = (short_side * 0.02).round() as i64;
+    let max_width = width as f32 * MAX_STAMP_WIDTH_RATIO;
+
+    let mut size = (short_side * STAMP_SIZE_RATIO).clamp(MIN_STAMP_PX, MAX_STAMP_PX);
+    let (text_w, _) = text_size(PxScale::from(size), font, text);
+    // Padding is 0.3em per side, so the box is text width + 0.6em
+    let box_w = text_w as f32 + size * 0.6;
+    if box_w > max_width {
+        size *= max_width / box_w;
+    }
+    if size < MIN_STAMP_PX {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Stamp text too long for a {}px wide image", width),
+        });
+    }
+
+    let scale = PxScale::from(size);
+    let (text_w, text_h) = text_size(scale, font, text);
+    let pad = (size * 0.3).round() as i64;
+    let box_w = text_w as i64 + 2 * pad;
+    let box_h = text_h as i64 + 2 * pad;
+    let box_x = (width as i64 - margin - box_w).max(0);
+    let box_y = (height as i64 - margin - box_h).max(0);
+
+    let x_end = (box_x + box_w).min(width as i64) as u32;
+    let y_end = (box_y + box_h).min(height as i64) as u32;
+    for y in box_y as u32..y_end {
+        for x in box_x as u32..x_end {
+            let pixel = img.get_pixel_mut(x, y);
+            for channel in pixel.0.iter_mut() {
+                *channel = (*channel as f32 * (1.0 - BOX_ALPHA) + 255.0 * BOX_ALPHA).round() as u8;
+            }
+        }
+    }
+    draw_text_mut(img, TEXT_COLOR, (box_x + pad) as i32, (box_y + pad) as i32, scale, font, text);
+    Ok(size)
+}
+
+/// Stamp `text` (e.g. "TX-20240105-0012 2024-01-05") onto a stored `{id}.jpg` and
+/// overwrite it (atomically); `quality` defaults to 75
+/// There is no undo: keep the original elsewhere or re-upload to get a clean copy.
+/// The thumbnail is left unstamped
+#[tauri::command]
+#[tracing::instrument(
+    skip_all,
+    fields(trace_id = trace_id.as_deref().unwrap_or("no-trace"), image_id = %image_id),
+    err(Display)
+)]
+pub async fn stamp_image(
+    image_id: String,
+    text: String,
+    quality: Option<u8>,
+    trace_id: Option<String>,
+) -> Result<StampResult, AppError> {
+    validate_image_id(&image_id)?;
+    let quality = parse_quality(quality)?;
+    let font = load_font()?;
+    validate_stamp_text(&font, &text)?;
+
+    run_blocking(move || {
+        let path = get_data_dir()?.join(format!("{}.jpg", image_id));
+        if !path.exists() {
+            return Err(AppError::FileNotFound { path: path.to_string_lossy().to_string() });
+        }
+        let original = fs::read(&path).map_err(|e| AppError::io_at(&path, "Failed to read image", e))?;
+        let mut img = image::load_from_memory(&original)
+            .map_err(|e| AppError::DecodeFailed { reason: format!("Failed to open image: {}", e) })?
+            .to_rgb8();
+        let font_size = draw_stamp(&mut img, &font, &text)?;
+
+        let mut encoded = Vec::new();
+        JpegEncoder::new_with_quality(&mut encoded, quality)
+            .encode_image(&img)
+            .map_err(|e| AppError::EncodeFailed { reason: format!("Failed to encode image: {}", e) })?;
+        write_atomic(&path, &encoded).map_err(|e| AppError::io("Failed to write stamped image", e))?;
+
+        Ok(StampResult {
+            md5: format!("{:x}", md5::compute(&encoded)),
+            width: img.width(),
+            height: img.height(),
+            compressed_size: encoded.len() as u64,
+            font_size,
+        })
+    })
+    .await
+}
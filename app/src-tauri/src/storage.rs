@@ -0,0 +1,1080 @@
+//! Local image storage directory and file operations
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tauri::State;
+
+use crate::config::{config_path, load_config, read_config, update_config};
+use crate::crypto::ENCRYPTED_EXTENSION;
+use crate::error::AppError;
+use crate::hash::{hash_file, HashAlgorithm};
+use crate::logging::{get_logs_dir, LogWriter};
+use crate::queue::queue_path;
+use crate::quota::ledger_path;
+use crate::task::run_blocking;
+
+/// The app's home directory for logs and small state files (~/.yorutsuke/), not created
+pub fn app_home_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(std::env::temp_dir).join(".yorutsuke")
+}
+
+/// Get the app's home directory, creating it if needed
+pub fn get_app_home_dir() -> PathBuf {
+    let app_home = app_home_dir();
+    fs::create_dir_all(&app_home).ok();
+    app_home
+}
+
+/// Platform-standard storage root (parent of `images/`)
+/// - macOS: ~/Library/Application Support/yorutsuke-v2/
+/// - Linux: ~/.local/share/yorutsuke-v2/
+/// - Windows: C:\Users\<user>\AppData\Local\yorutsuke-v2\
+///
+/// Falls back to the home directory; never to the temp dir, which the OS may wipe
+fn default_storage_root() -> Result<PathBuf, AppError> {
+    let base = dirs::data_local_dir()
+        .or_else(dirs::data_dir)
+        .or_else(dirs::home_dir)
+        .ok_or_else(|| AppError::IoError {
+            reason: "No data directory available for image storage; choose a storage location in Settings"
+                .to_string(),
+        })?;
+    Ok(base.join("yorutsuke-v2"))
+}
+
+/// Storage root from config.json, or the platform default when none is set
+pub fn storage_root() -> Result<PathBuf, AppError> {
+    match read_config().storage_root {
+        Some(root) => Ok(PathBuf::from(root)),
+        None => default_storage_root(),
+    }
+}
+
+/// `{storage root}/images/`, where the root is user-configurable (set_storage_root)
+/// and defaults to the platform-standard data directory; not created
+pub fn images_dir() -> Result<PathBuf, AppError> {
+    Ok(storage_root()?.join("images"))
+}
+
+/// Get the app's data directory for storing compressed images, creating it if needed
+/// Fails when the directory can't be created, rather than letting later writes fail silently
+pub fn get_data_dir() -> Result<PathBuf, AppError> {
+    let images_dir = images_dir()?;
+    fs::create_dir_all(&images_dir)
+        .map_err(|e| AppError::io_at(&images_dir, "Failed to create images directory", e))?;
+    Ok(images_dir)
+}
+
+/// Check that the images directory exists and accepts writes (call once at startup)
+/// Returns the directory path; an error here means images can't be stored at all
+#[tauri::command]
+pub fn ensure_storage_ready() -> Result<String, AppError> {
+    let dir = get_data_dir()?;
+    ensure_writable_dir(&dir)?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
+/// Distinguishes temp files of concurrent writes to the same target
+static ATOMIC_WRITE_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Replace `path` with `bytes` so readers, and the file after a crash, see either the
+/// old or the new contents, never a partial file
+/// Writes a temp file in the same directory, fsyncs it, then renames it over `path`
+/// (on Unix the directory is synced too, so the rename itself survives power loss)
+/// Every JSON state file and in-place image rewrite goes through this
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    write_atomic_with(path, |file| file.write_all(bytes))
+}
+
+/// write_atomic with the temp file's contents produced by `write`
+fn write_atomic_with(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let tmp = write_staged_with(path, write)?;
+    commit_staged(&tmp, path)
+}
+
+/// First half of write_atomic: write `bytes` to a fsynced temp file next to `path` and
+/// return it, leaving `path` untouched until commit_staged
+/// Lets a caller check the new file (or stage several) before anything is replaced;
+/// a staged file that isn't committed must be removed by the caller
+pub fn write_staged(path: &Path, bytes: &[u8]) -> std::io::Result<PathBuf> {
+    use std::io::Write;
+    write_staged_with(path, |file| file.write_all(bytes))
+}
+
+fn write_staged_with(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> std::io::Result<()>,
+) -> std::io::Result<PathBuf> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        ATOMIC_WRITE_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ));
+    let tmp = PathBuf::from(tmp_name);
+
+    let result = fs::File::create(&tmp).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()
+    });
+    if let Err(e) = result {
+        fs::remove_file(&tmp).ok();
+        return Err(e);
+    }
+    Ok(tmp)
+}
+
+/// Second half of write_atomic: rename a file from write_staged over `path`
+/// The staged file is removed if the rename fails
+pub fn commit_staged(tmp: &Path, path: &Path) -> std::io::Result<()> {
+    if let Err(e) = fs::rename(tmp, path) {
+        fs::remove_file(tmp).ok();
+        return Err(e);
+    }
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::File::open(dir).and_then(|dir| dir.sync_all()).ok();
+    }
+    Ok(())
+}
+
+/// Create `dir` if needed and prove it accepts writes
+pub fn ensure_writable_dir(dir: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(dir).map_err(|e| AppError::io("Failed to create directory", e))?;
+    let probe = dir.join(".yorutsuke-write-test");
+    fs::write(&probe, b"").map_err(|e| AppError::io("Directory is not writable", e))?;
+    fs::remove_file(&probe).ok();
+    Ok(())
+}
+
+/// Validate a new storage root: absolute, no `..`, and writable
+/// Unlike output directories it may live outside home (e.g. an external drive)
+fn validate_storage_root(path: &str) -> Result<PathBuf, AppError> {
+    let root = Path::new(path);
+    if !root.is_absolute() || root.components().any(|c| c == std::path::Component::ParentDir) {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid storage root: {} (expected an absolute path without ..)", path),
+        });
+    }
+    ensure_writable_dir(&root.join("images"))?;
+    Ok(root.to_path_buf())
+}
+
+/// Get the current storage root (the directory that holds `images/`)
+#[tauri::command]
+pub fn get_storage_root() -> Result<String, AppError> {
+    Ok(storage_root()?.to_string_lossy().to_string())
+}
+
+/// Point image storage at a new root (e.g. an external drive), persisted to config.json
+/// Existing images are NOT moved; use migrate_storage for that
+#[tauri::command]
+pub fn set_storage_root(path: String) -> Result<(), AppError> {
+    let root = validate_storage_root(&path)?;
+    update_config(|config| config.storage_root = Some(root.to_string_lossy().to_string()))
+}
+
+/// Summary of a storage migration
+#[derive(serde::Serialize)]
+pub struct StorageMigrationResult {
+    pub moved_count: u32,
+    pub moved_bytes: u64,
+    pub images_dir: String,
+}
+
+/// Move every stored image to `{new_root}/images/`, then switch the config to the new root
+/// If any move fails (or the config can't be saved), already-moved files are moved back
+/// and the config is left unchanged
+/// Runs on a blocking worker: copying to another drive can take minutes
+#[tauri::command]
+pub async fn migrate_storage(new_root: String) -> Result<StorageMigrationResult, AppError> {
+    run_blocking(move || {
+        let root = validate_storage_root(&new_root)?;
+        // A damaged config fails here, before any image is moved, rather than at the switch
+        load_config()?;
+        let old_dir = get_data_dir()?;
+        let new_dir = root.join("images");
+        let mut result = StorageMigrationResult {
+            moved_count: 0,
+            moved_bytes: 0,
+            images_dir: new_dir.to_string_lossy().to_string(),
+        };
+
+        let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+        // Roll back so images are never split across two roots
+        let roll_back = |moved: &[(PathBuf, PathBuf)]| {
+            for (original, migrated) in moved.iter().rev() {
+                move_path(migrated, original).ok();
+            }
+        };
+        if resolve_for_compare(&old_dir) != resolve_for_compare(&new_dir) {
+            let entries = fs::read_dir(&old_dir)
+                .map_err(|e| AppError::io("Failed to read images directory", e))?;
+            for entry in entries.flatten() {
+                let Ok(metadata) = fs::symlink_metadata(entry.path()) else { continue };
+                if !metadata.is_file() {
+                    continue;
+                }
+                let from = entry.path();
+                let to = new_dir.join(entry.file_name());
+                if let Err(e) = move_path(&from, &to) {
+                    roll_back(&moved);
+                    return Err(e);
+                }
+                moved.push((from, to));
+                result.moved_count += 1;
+                result.moved_bytes += metadata.len();
+            }
+        }
+
+        if let Err(e) = update_config(|config| config.storage_root = Some(root.to_string_lossy().to_string())) {
+            roll_back(&moved);
+            return Err(e);
+        }
+        Ok(result)
+    })
+    .await
+}
+
+/// Validate a caller-chosen output directory (e.g. an export folder)
+/// It must be absolute, contain no `..`, and resolve to somewhere under the user's
+/// home directory; it is created if missing and must be writable
+pub fn resolve_output_dir(dir: &str) -> Result<PathBuf, AppError> {
+    let path = Path::new(dir);
+    if !path.is_absolute() || path.components().any(|c| c == std::path::Component::ParentDir) {
+        return Err(AppError::InvalidArgument {
+            reason: format!("Invalid output directory: {} (expected an absolute path without ..)", dir),
+        });
+    }
+    // Canonicalize so symlinks can't point the write outside the home directory
+    // The deepest existing ancestor is checked before anything is created
+    let home = dirs::home_dir().and_then(|home| fs::canonicalize(home).ok());
+    let inside_home = |candidate: &Path| {
+        fs::canonicalize(candidate)
+            .ok()
+            .zip(home.as_ref())
+            .is_some_and(|(resolved, home)| resolved.starts_with(home))
+    };
+    let outside_home = || AppError::InvalidArgument {
+        reason: format!("Invalid output directory: {} (must be inside the home directory)", dir),
+    };
+    if !path.ancestors().find(|ancestor| ancestor.exists()).is_some_and(inside_home) {
+        return Err(outside_home());
+    }
+    fs::create_dir_all(path).map_err(|e| AppError::io("Failed to create output directory", e))?;
+    let resolved = fs::canonicalize(path).map_err(|e| AppError::io("Failed to resolve output directory", e))?;
+    if !inside_home(&resolved) {
+        return Err(outside_home());
+    }
+
+    ensure_writable_dir(&resolved)?;
+    Ok(resolved)
+}
+
+/// Fail with `InsufficientSpace` if the volume holding `dir` has less than `needed` bytes free
+/// If free space cannot be determined the write is allowed to proceed
+pub fn ensure_free_space(dir: &Path, needed: u64) -> Result<(), AppError> {
+    match fs2::available_space(dir) {
+        Ok(available) if available < needed => Err(AppError::InsufficientSpace { needed, available }),
+        _ => Ok(()),
+    }
+}
+
+/// Waits before each delete retry on Windows (at most 350ms in total)
+#[cfg(windows)]
+const DELETE_RETRY_DELAYS: [std::time::Duration; 3] = [
+    std::time::Duration::from_millis(50),
+    std::time::Duration::from_millis(100),
+    std::time::Duration::from_millis(200),
+];
+
+/// Whether a failed delete is a transient lock held by another process
+/// (antivirus scan, webview still displaying the image)
+#[cfg(windows)]
+fn is_sharing_violation(e: &std::io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION / ERROR_LOCK_VIOLATION; pending deletes report access denied
+    matches!(e.raw_os_error(), Some(32 | 33)) || e.kind() == std::io::ErrorKind::PermissionDenied
+}
+
+/// Remove a file, retrying with backoff while Windows reports it in use
+/// Other platforms don't lock open files, so a single attempt is made there
+fn remove_file_with_retry(path: &Path) -> std::io::Result<()> {
+    #[cfg(windows)]
+    for delay in DELETE_RETRY_DELAYS {
+        match fs::remove_file(path) {
+            Err(e) if is_sharing_violation(&e) => std::thread::sleep(delay),
+            result => return result,
+        }
+    }
+    fs::remove_file(path)
+}
+
+/// Delete a local file
+/// On Windows, files briefly locked by another process are retried before failing
+#[tauri::command]
+pub fn delete_file(path: String) -> Result<(), AppError> {
+    let file_path = Path::new(&path);
+    if !file_path.exists() {
+        return Ok(()); // Not an error if file doesn't exist
+    }
+    remove_file_with_retry(file_path)
+        .map_err(|e| AppError::io("Failed to delete file", e))
+}
+
+/// Disk usage of the images directory
+#[derive(serde::Serialize)]
+pub struct DirUsage {
+    pub total_bytes: u64,
+    pub file_count: u64,
+}
+
+/// Sum file sizes under `dir` recursively without following symlinks
+fn dir_usage(dir: &Path, usage: &mut DirUsage) -> Result<(), AppError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(AppError::io("Failed to read images directory", e)),
+    };
+
+    for entry in entries.flatten() {
+        // file_type() does not traverse symlinks, so links out of the directory are skipped
+        let Ok(file_type) = entry.file_type() else { continue };
+        if file_type.is_dir() {
+            dir_usage(&entry.path(), usage)?;
+        } else if file_type.is_file() {
+            if let Ok(metadata) = entry.metadata() {
+                usage.total_bytes += metadata.len();
+                usage.file_count += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Report total bytes and file count stored in the images directory
+#[tauri::command]
+pub fn get_images_dir_size() -> Result<DirUsage, AppError> {
+    let mut usage = DirUsage { total_bytes: 0, file_count: 0 };
+    dir_usage(&get_data_dir()?, &mut usage)?;
+    Ok(usage)
+}
+
+/// Get the resolved images directory path (for the Settings screen)
+#[tauri::command]
+pub fn get_images_dir_path() -> Result<String, AppError> {
+    Ok(get_data_dir()?.to_string_lossy().to_string())
+}
+
+/// Show `dir` in Finder/Explorer/the desktop file manager
+/// Fails with `OpenFailed` when no file manager can be launched (e.g. headless Linux)
+pub fn open_in_file_manager(dir: &Path) -> Result<(), AppError> {
+    opener::open(dir).map_err(|e| AppError::OpenFailed {
+        reason: format!("Failed to open {}: {}", dir.display(), e),
+    })
+}
+
+/// Open the images directory in the OS file manager (created first if missing)
+#[tauri::command]
+pub fn open_images_dir() -> Result<(), AppError> {
+    open_in_file_manager(&get_data_dir()?)
+}
+
+/// Result of an end-to-end storage probe
+#[derive(serde::Serialize)]
+pub struct StorageHealth {
+    pub writable: bool,
+    pub readable: bool,
+    pub fsync_ok: bool,
+    pub dir_path: String,
+    /// OS error of the first failing step, e.g. "write: Permission denied (os error 13)"
+    pub error: Option<String>,
+}
+
+/// Bytes written by check_storage_health
+const HEALTH_PROBE_BYTES: &[u8] = b"yorutsuke storage health probe";
+
+/// Verify the images directory actually works: write, fsync, read back and delete a probe file
+/// A definitive answer instead of trusting that create_dir_all succeeded
+#[tauri::command]
+pub fn check_storage_health() -> StorageHealth {
+    use std::io::{Read, Write};
+
+    let dir = match get_data_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            return StorageHealth {
+                writable: false,
+                readable: false,
+                fsync_ok: false,
+                dir_path: storage_root().map(|root| root.join("images").to_string_lossy().to_string()).unwrap_or_default(),
+                error: Some(format!("create: {}", e)),
+            }
+        }
+    };
+    let mut health = StorageHealth {
+        writable: false,
+        readable: false,
+        fsync_ok: false,
+        dir_path: dir.to_string_lossy().to_string(),
+        error: None,
+    };
+    let probe = dir.join(format!(".health-{}", std::process::id()));
+
+    let step = |name: &str, e: std::io::Error| Some(format!("{}: {}", name, e));
+    let written = fs::File::create(&probe).and_then(|mut file| {
+        file.write_all(HEALTH_PROBE_BYTES)?;
+        file.flush()?;
+        Ok(file)
+    });
+    match written {
+        Ok(file) => {
+            health.writable = true;
+            match file.sync_all() {
+                Ok(()) => health.fsync_ok = true,
+                Err(e) => health.error = step("fsync", e),
+            }
+        }
+        Err(e) => health.error = step("write", e),
+    }
+
+    if health.writable {
+        let mut contents = Vec::new();
+        match fs::File::open(&probe).and_then(|mut file| file.read_to_end(&mut contents)) {
+            Ok(_) if contents == HEALTH_PROBE_BYTES => health.readable = true,
+            Ok(_) => {
+                health.error = health.error.or_else(|| Some("read: contents did not match what was written".to_string()))
+            }
+            Err(e) => health.error = health.error.or_else(|| step("read", e)),
+        }
+        if let Err(e) = fs::remove_file(&probe) {
+            health.error = health.error.or_else(|| step("delete", e));
+        }
+    } else {
+        // The create may have succeeded before the write failed
+        fs::remove_file(&probe).ok();
+    }
+
+    health
+}
+
+/// Longest accepted image id (UUIDs are 36, MD5 hex ids 32)
+const MAX_IMAGE_ID_LEN: usize = 64;
+
+/// Reject image ids that could escape the images directory when used in a filename
+/// Ids must be 1-64 ASCII letters, digits, `-` or `_`; call before any filesystem access
+pub fn validate_image_id(id: &str) -> Result<(), AppError> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_IMAGE_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::InvalidId { id: id.to_string() })
+    }
+}
+
+/// Extensions written by compress_image (and its thumbnails); `enc` is encrypted storage
+const STORED_IMAGE_EXTENSIONS: &[&str] = &["jpg", "webp", "png", ENCRYPTED_EXTENSION];
+
+/// Suffix of untouched source copies kept by compress_image's `keepOriginal`
+pub const ORIGINAL_SUFFIX: &str = "_original";
+
+/// Parse a stored image filename into `(image_id, is_thumbnail)`
+/// Matches `{id}.jpg`, `{id}.webp`, `{id}.png`, `{id}.enc` and `{id}_thumb.jpg`; anything else is `None`
+/// Kept originals (`{id}_original.{ext}`) are deliberately not matched, so listing,
+/// stats and orphan cleanup never touch audit copies
+pub fn parse_stored_image_name(filename: &str) -> Option<(&str, bool)> {
+    let (stem, extension) = filename.rsplit_once('.')?;
+    if !STORED_IMAGE_EXTENSIONS.contains(&extension) || stem.ends_with(ORIGINAL_SUFFIX) {
+        return None;
+    }
+    match stem.strip_suffix("_thumb") {
+        Some(id) if extension == "jpg" => Some((id, true)),
+        Some(_) => None,
+        None => Some((stem, false)),
+    }
+}
+
+/// Whether `filename` is a `{id}.jpg` kept next to its `{id}.webp` by
+/// migrate_store_to_webp without `deleteOriginals`
+/// The WebP is then the stored image and the JPEG only a backup, so listing, stats,
+/// dedup and lookups skip it (orphan cleanup and wipes still remove it with its id)
+pub fn is_superseded_jpeg(dir: &Path, filename: &str) -> bool {
+    filename
+        .strip_suffix(".jpg")
+        .filter(|stem| !stem.ends_with("_thumb") && !stem.ends_with(ORIGINAL_SUFFIX))
+        .is_some_and(|id| dir.join(format!("{}.webp", id)).is_file())
+}
+
+/// Path of the stored plaintext image for `id` (`{id}.webp`, `.jpg` or `.png`), if any
+/// WebP is tried first so a JPEG kept by the WebP migration is never served instead;
+/// encrypted `{id}.enc` files are not returned since they can't be decoded directly
+pub fn find_stored_image(id: &str) -> Result<Option<PathBuf>, AppError> {
+    validate_image_id(id)?;
+    let dir = get_data_dir()?;
+    Ok(["webp", "jpg", "png"]
+        .iter()
+        .map(|extension| dir.join(format!("{}.{}", id, extension)))
+        .find(|path| path.is_file()))
+}
+
+/// A file in the images directory, as listed by list_stored_images
+#[derive(serde::Serialize)]
+pub struct StoredImage {
+    pub id: String,
+    pub path: String,
+    pub size: u64,
+    /// RFC 3339 (UTC)
+    pub modified_at: String,
+    pub is_thumbnail: bool,
+}
+
+/// List stored images and thumbnails with size and modification time, newest first
+/// Files that don't match a stored image name are skipped, as are JPEGs kept next to
+/// their migrated WebP
+#[tauri::command]
+pub fn list_stored_images() -> Result<Vec<StoredImage>, AppError> {
+    let dir = get_data_dir()?;
+    let entries = fs::read_dir(&dir).map_err(|e| AppError::io("Failed to read images directory", e))?;
+
+    let mut images: Vec<(std::time::SystemTime, StoredImage)> = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(filename) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let Some((id, is_thumbnail)) = parse_stored_image_name(filename) else { continue };
+        if is_superseded_jpeg(&dir, filename) {
+            continue;
+        }
+        let Ok(metadata) = fs::symlink_metadata(&path) else { continue };
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata.modified().unwrap_or(std::time::UNIX_EPOCH);
+
+        images.push((
+            modified,
+            StoredImage {
+                id: id.to_string(),
+                path: path.to_string_lossy().to_string(),
+                size: metadata.len(),
+                modified_at: chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339(),
+                is_thumbnail,
+            },
+        ));
+    }
+
+    images.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    Ok(images.into_iter().map(|(_, image)| image).collect())
+}
+
+/// Aggregate numbers for the storage dashboard
+#[derive(serde::Serialize)]
+pub struct StoredImageStats {
+    pub count: u64,
+    pub total_bytes: u64,
+    pub avg_bytes: u64,
+    pub largest_id: Option<String>,
+    /// RFC 3339 (UTC); None when nothing is stored
+    pub oldest_modified: Option<String>,
+    pub newest_modified: Option<String>,
+    /// File extension (e.g. "jpg") to number of files
+    pub format_breakdown: std::collections::BTreeMap<String, u64>,
+}
+
+/// Compute storage stats in one pass over the images directory (thumbnails included,
+/// JPEGs kept next to their migrated WebP not)
+/// An empty directory yields zero counts rather than an error
+#[tauri::command]
+pub fn get_stored_image_stats() -> Result<StoredImageStats, AppError> {
+    let dir = get_data_dir()?;
+    let entries = fs::read_dir(&dir).map_err(|e| AppError::io("Failed to read images directory", e))?;
+
+    let mut stats = StoredImageStats {
+        count: 0,
+        total_bytes: 0,
+        avg_bytes: 0,
+        largest_id: None,
+        oldest_modified: None,
+        newest_modified: None,
+        format_breakdown: std::collections::BTreeMap::new(),
+    };
+    let mut largest = 0u64;
+    let mut oldest: Option<std::time::SystemTime> = None;
+    let mut newest: Option<std::time::SystemTime> = None;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(filename) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let Some((id, _)) = parse_stored_image_name(filename) else { continue };
+        if is_superseded_jpeg(&dir, filename) {
+            continue;
+        }
+        let Ok(metadata) = fs::symlink_metadata(&path) else { continue };
+        if !metadata.is_file() {
+            continue;
+        }
+
+        stats.count += 1;
+        stats.total_bytes += metadata.len();
+        if stats.largest_id.is_none() || metadata.len() > largest {
+            largest = metadata.len();
+            stats.largest_id = Some(id.to_string());
+        }
+        if let Ok(modified) = metadata.modified() {
+            oldest = Some(oldest.map_or(modified, |t| t.min(modified)));
+            newest = Some(newest.map_or(modified, |t| t.max(modified)));
+        }
+        if let Some((_, extension)) = filename.rsplit_once('.') {
+            *stats.format_breakdown.entry(extension.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    stats.avg_bytes = stats.total_bytes.checked_div(stats.count).unwrap_or(0);
+    let to_rfc3339 = |time: std::time::SystemTime| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339();
+    stats.oldest_modified = oldest.map(to_rfc3339);
+    stats.newest_modified = newest.map(to_rfc3339);
+    Ok(stats)
+}
+
+/// A stored image that failed to decode
+#[derive(serde::Serialize)]
+pub struct CorruptImage {
+    pub id: String,
+    pub path: String,
+    pub error: String,
+}
+
+/// Fully decode stored images and report the ones that fail (e.g. truncated writes)
+/// Runs on a blocking worker so the scan never stalls IPC; `limit` caps how many
+/// images are checked (all by default), in filename order
+#[tauri::command]
+pub async fn scan_corrupt_images(limit: Option<usize>) -> Result<Vec<CorruptImage>, AppError> {
+    run_blocking(move || {
+        let entries = fs::read_dir(get_data_dir()?)
+            .map_err(|e| AppError::io("Failed to read images directory", e))?;
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name().and_then(|n| n.to_str()).and_then(parse_stored_image_name).is_some())
+            // Encrypted files can't be decoded without the passphrase
+            .filter(|path| path.extension().and_then(|e| e.to_str()) != Some(ENCRYPTED_EXTENSION))
+            .collect();
+        paths.sort();
+
+        let mut corrupt = Vec::new();
+        for path in paths.into_iter().take(limit.unwrap_or(usize::MAX)) {
+            if let Err(e) = image::open(&path) {
+                let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+                let id = parse_stored_image_name(filename).map(|(id, _)| id.to_string()).unwrap_or_default();
+                corrupt.push(CorruptImage {
+                    id,
+                    path: path.to_string_lossy().to_string(),
+                    error: e.to_string(),
+                });
+            }
+        }
+        Ok(corrupt)
+    })
+    .await
+}
+
+/// Summary of an orphan cleanup pass
+#[derive(serde::Serialize)]
+pub struct OrphanCleanupResult {
+    pub deleted_count: u32,
+    pub freed_bytes: u64,
+    pub dry_run: bool,
+}
+
+/// Delete stored images whose id is not in `referenced_ids`
+/// The frontend (SQL plugin) knows which ids are live; with `dry_run` nothing is deleted
+/// and the result reports what would have been removed
+#[tauri::command]
+pub fn cleanup_orphan_images(
+    referenced_ids: Vec<String>,
+    dry_run: bool,
+) -> Result<OrphanCleanupResult, AppError> {
+    let referenced: std::collections::HashSet<String> = referenced_ids.into_iter().collect();
+    let mut result = OrphanCleanupResult { deleted_count: 0, freed_bytes: 0, dry_run };
+
+    let entries = fs::read_dir(get_data_dir()?)
+        .map_err(|e| AppError::io("Failed to read images directory", e))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(filename) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let Some((id, _)) = parse_stored_image_name(filename) else { continue };
+        if referenced.contains(id) {
+            continue;
+        }
+        let Ok(metadata) = fs::symlink_metadata(&path) else { continue };
+        if !metadata.is_file() {
+            continue;
+        }
+
+        if dry_run || fs::remove_file(&path).is_ok() {
+            result.deleted_count += 1;
+            result.freed_bytes += metadata.len();
+        }
+    }
+
+    Ok(result)
+}
+
+/// Stored images with byte-identical content
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct DuplicateGroup {
+    /// SHA-256 of the shared content
+    pub hash: String,
+    /// The copy that stays: the oldest file (ties broken by id)
+    pub kept_id: String,
+    pub duplicate_ids: Vec<String>,
+}
+
+/// Result of dedup_store
+#[derive(serde::Serialize)]
+pub struct DedupResult {
+    pub groups: Vec<DuplicateGroup>,
+    /// Removed id -> kept id, for repointing records; with `dry_run` the mapping that
+    /// would apply. Ids whose files could not be deleted are left out
+    pub removed: std::collections::BTreeMap<String, String>,
+    /// Duplicates that failed to delete (still stored, not in `removed`)
+    pub failed_ids: Vec<String>,
+    /// Bytes of the duplicate images and their thumbnails
+    pub freed_bytes: u64,
+    /// Ids stored in more than one format; never removed since one hash can't stand for them
+    pub skipped_ids: Vec<String>,
+    pub dry_run: bool,
+}
+
+/// A main stored image considered for dedup
+struct DedupCandidate {
+    id: String,
+    hash: String,
+    modified: std::time::SystemTime,
+}
+
+/// Group candidates by hash and pick a kept id per group of two or more
+/// Groups are ordered by kept id so results are stable
+fn plan_dedup(mut candidates: Vec<DedupCandidate>) -> Vec<DuplicateGroup> {
+    candidates.sort_by(|a, b| a.modified.cmp(&b.modified).then_with(|| a.id.cmp(&b.id)));
+    let mut by_hash: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
+    for candidate in candidates {
+        by_hash.entry(candidate.hash).or_default().push(candidate.id);
+    }
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(_, ids)| ids.len() > 1)
+        .map(|(hash, mut ids)| {
+            let kept_id = ids.remove(0);
+            DuplicateGroup { hash, kept_id, duplicate_ids: ids }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.kept_id.cmp(&b.kept_id));
+    groups
+}
+
+/// Find stored images with identical content (SHA-256 of the file) under different ids
+/// and, unless `dry_run`, delete all but the oldest copy of each, thumbnails included.
+/// Returns the removed -> kept id mapping so the frontend can repoint its records.
+/// Only exact byte copies match (the same photo compressed twice with different
+/// settings does not); encrypted files never match since each has its own nonce.
+/// Kept originals (`{id}_original.*`) are left alone
+#[tauri::command]
+pub async fn dedup_store(dry_run: bool) -> Result<DedupResult, AppError> {
+    run_blocking(move || {
+        let dir = get_data_dir()?;
+        let entries = fs::read_dir(&dir).map_err(|e| AppError::io("Failed to read images directory", e))?;
+
+        // Main files per id, and bytes per id with thumbnails
+        let mut files: std::collections::BTreeMap<String, Vec<(PathBuf, fs::Metadata)>> =
+            std::collections::BTreeMap::new();
+        let mut sizes: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(filename) = path.file_name().and_then(|n| n.to_str()) else { continue };
+            let Some((id, is_thumbnail)) = parse_stored_image_name(filename) else { continue };
+            let Ok(metadata) = fs::symlink_metadata(&path) else { continue };
+            if !metadata.is_file() {
+                continue;
+            }
+            *sizes.entry(id.to_string()).or_insert(0) += metadata.len();
+            // A JPEG kept by the WebP migration is removed with its id but not compared
+            if !is_thumbnail && !is_superseded_jpeg(&dir, filename) {
+                files.entry(id.to_string()).or_default().push((path, metadata));
+            }
+        }
+
+        let mut skipped_ids = Vec::new();
+        let mut candidates = Vec::new();
+        for (id, mut paths) in files {
+            if paths.len() > 1 {
+                skipped_ids.push(id);
+                continue;
+            }
+            let (path, metadata) = paths.remove(0);
+            // A file that vanished or can't be read just isn't a candidate
+            let Ok(hash) = hash_file(&path, HashAlgorithm::Sha256) else { continue };
+            candidates.push(DedupCandidate {
+                id,
+                hash,
+                modified: metadata.modified().unwrap_or(std::time::UNIX_EPOCH),
+            });
+        }
+
+        let groups = plan_dedup(candidates);
+        let mut result = DedupResult {
+            groups: Vec::new(),
+            removed: std::collections::BTreeMap::new(),
+            failed_ids: Vec::new(),
+            freed_bytes: 0,
+            skipped_ids,
+            dry_run,
+        };
+        for group in &groups {
+            for id in &group.duplicate_ids {
+                if !dry_run {
+                    // Thumbnail first: if the image itself then fails to delete, the id
+                    // is still fully usable and correctly reported as not removed
+                    let deleted = std::iter::once(dir.join(format!("{}_thumb.jpg", id)))
+                        .chain(STORED_IMAGE_EXTENSIONS.iter().map(|extension| dir.join(format!("{}.{}", id, extension))))
+                        .filter(|path| path.exists())
+                        .all(|path| remove_file_with_retry(&path).is_ok());
+                    if !deleted {
+                        tracing::warn!(image_id = %id, "dedup_delete_failed");
+                        result.failed_ids.push(id.clone());
+                        continue;
+                    }
+                }
+                result.removed.insert(id.clone(), group.kept_id.clone());
+                result.freed_bytes += sizes.get(id).copied().unwrap_or(0);
+            }
+        }
+        tracing::info!(
+            groups = groups.len(),
+            removed = result.removed.len(),
+            failed = result.failed_ids.len(),
+            dry_run,
+            "dedup_store"
+        );
+        result.groups = groups;
+        Ok(result)
+    })
+    .await
+}
+
+/// What wipe_local_data removed
+#[derive(serde::Serialize)]
+pub struct WipeResult {
+    /// Entries removed from the images directory
+    pub images_removed: u64,
+    /// config.json / queue.json / quota.json removed
+    pub state_files_removed: u32,
+    /// Entries removed from the logs directory (0 unless `include_logs`)
+    pub logs_removed: u64,
+}
+
+/// Delete everything inside `dir`, returning how many entries were removed
+/// Symlinks are unlinked, never followed; a symlinked `dir` itself is refused so a wipe
+/// can't reach outside the app's directories. A missing `dir` counts as empty
+fn clear_dir(dir: &Path) -> Result<u64, AppError> {
+    match fs::symlink_metadata(dir) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            return Err(AppError::InvalidArgument {
+                reason: format!("Refusing to wipe symlinked directory: {}", dir.display()),
+            })
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(AppError::io_at(dir, "Failed to read directory", e)),
+    }
+
+    let entries = fs::read_dir(dir).map_err(|e| AppError::io_at(dir, "Failed to read directory", e))?;
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else { continue };
+        // remove_dir_all doesn't follow symlinks inside the tree either
+        let result = if file_type.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        result.map_err(|e| AppError::io_at(&path, "Failed to delete", e))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Delete all local data for "reset app" / sign-out: stored images, the saved
+/// config, compress queue and upload ledger, and with `include_logs` the log files
+/// Only the app's own directories are touched; safe to call again on an empty install
+#[tauri::command]
+pub fn wipe_local_data(writer: State<'_, LogWriter>, include_logs: bool) -> Result<WipeResult, AppError> {
+    // Resolve before config.json (which may hold a custom storage root) is removed
+    let images_dir = images_dir()?;
+    let mut result = WipeResult { images_removed: clear_dir(&images_dir)?, state_files_removed: 0, logs_removed: 0 };
+
+    for state_file in [config_path(), queue_path(), ledger_path()] {
+        match fs::remove_file(&state_file) {
+            Ok(()) => result.state_files_removed += 1,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(AppError::io_at(&state_file, "Failed to delete", e)),
+        }
+    }
+
+    if include_logs {
+        writer.close();
+        result.logs_removed = clear_dir(&get_logs_dir())?;
+    }
+    Ok(result)
+}
+
+/// Resolve a path for comparison even if the file itself doesn't exist yet
+fn resolve_for_compare(path: &Path) -> PathBuf {
+    if let Ok(resolved) = fs::canonicalize(path) {
+        return resolved;
+    }
+    match (path.parent().and_then(|p| fs::canonicalize(p).ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Copy to a temp name next to `to`, rename into place, then delete `from`
+/// The final destination only ever appears complete
+fn copy_then_delete(from: &Path, to: &Path) -> std::io::Result<()> {
+    let mut partial_name = to.as_os_str().to_owned();
+    partial_name.push(".partial");
+    let partial = PathBuf::from(partial_name);
+
+    if let Err(e) = fs::copy(from, &partial).and_then(|_| fs::rename(&partial, to)) {
+        fs::remove_file(&partial).ok();
+        return Err(e);
+    }
+    fs::remove_file(from)
+}
+
+/// Move a file atomically where possible
+/// Tries `rename` first (atomic on the same filesystem), falling back to
+/// copy-then-delete across devices; creates the destination's parent directory
+#[tauri::command]
+pub fn move_file(from: String, to: String) -> Result<(), AppError> {
+    move_path(Path::new(&from), Path::new(&to))
+}
+
+/// Shared implementation of move_file
+fn move_path(from_path: &Path, to_path: &Path) -> Result<(), AppError> {
+    if !from_path.exists() {
+        return Err(AppError::FileNotFound { path: from_path.to_string_lossy().to_string() });
+    }
+    if resolve_for_compare(from_path) == resolve_for_compare(to_path) {
+        return Ok(());
+    }
+
+    if let Some(parent) = to_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create destination directory", e))?;
+    }
+
+    match fs::rename(from_path, to_path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => copy_then_delete(from_path, to_path)
+            .map_err(|e| AppError::io("Failed to move file across devices", e)),
+        Err(e) => Err(AppError::io("Failed to move file", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_uuid_and_hash_ids() {
+        for id in ["2f1c7b9e-8a4d-4c3e-9b1a-0d6e5f4a3b2c", "9442dff95c87d9ff204eeb1deceee2e4", "img_1700000000_42"] {
+            assert!(validate_image_id(id).is_ok(), "{} should be valid", id);
+        }
+    }
+
+    #[test]
+    fn rejects_path_traversal_and_separators() {
+        for id in ["../../etc/passwd", "..", "a/b", "a\\b", "/etc/passwd", "C:evil", "id.jpg", "id\0"] {
+            let err = validate_image_id(id).unwrap_err();
+            assert_eq!(err.code(), "INVALID_ID", "{:?} should be rejected", id);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_overlong_and_non_ascii_ids() {
+        assert!(validate_image_id("").is_err());
+        assert!(validate_image_id(&"a".repeat(MAX_IMAGE_ID_LEN)).is_ok());
+        assert!(validate_image_id(&"a".repeat(MAX_IMAGE_ID_LEN + 1)).is_err());
+        assert!(validate_image_id("レシート").is_err());
+        assert!(validate_image_id("receipt 1").is_err());
+    }
+
+    #[test]
+    fn interrupted_atomic_write_leaves_target_intact() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("yorutsuke-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        write_atomic(&path, br#"{"version":1}"#).unwrap();
+
+        // Fail after half the new contents are out, as a crash or full disk would
+        let result = write_atomic_with(&path, |file| {
+            file.write_all(br#"{"vers"#)?;
+            Err(std::io::Error::other("interrupted"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read(&path).unwrap(), br#"{"version":1}"#);
+        // No temp file is left next to the target
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        write_atomic(&path, br#"{"version":2}"#).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), br#"{"version":2}"#);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn only_jpegs_next_to_their_webp_are_superseded() {
+        let dir = std::env::temp_dir().join(format!("yorutsuke-superseded-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["migrated.jpg", "migrated.webp", "plain.jpg", "migrated_thumb.jpg"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        assert!(is_superseded_jpeg(&dir, "migrated.jpg"));
+        assert!(!is_superseded_jpeg(&dir, "migrated.webp"));
+        assert!(!is_superseded_jpeg(&dir, "plain.jpg"));
+        assert!(!is_superseded_jpeg(&dir, "migrated_thumb.jpg"));
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn dedup_keeps_oldest_copy_of_each_hash() {
+        let at = |secs| std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+        let candidate = |id: &str, hash: &str, secs| DedupCandidate {
+            id: id.to_string(),
+            hash: hash.to_string(),
+            modified: at(secs),
+        };
+        let groups = plan_dedup(vec![
+            candidate("c", "h1", 30),
+            candidate("a", "h1", 10),
+            candidate("b", "h2", 20),
+            candidate("z", "h1", 10),
+            candidate("y", "h3", 5),
+        ]);
+        assert_eq!(
+            groups,
+            vec![DuplicateGroup {
+                hash: "h1".to_string(),
+                kept_id: "a".to_string(),
+                duplicate_ids: vec!["z".to_string(), "c".to_string()],
+            }]
+        );
+    }
+}
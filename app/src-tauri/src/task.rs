@@ -0,0 +1,55 @@
+//! Running CPU- and IO-heavy command bodies off the IPC worker
+
+use crate::error::AppError;
+
+/// Run `work` on the blocking thread pool and await its result
+/// Async commands use this so a slow decode never starves the Tauri runtime;
+/// the caller's tracing span is carried over to the worker thread
+pub async fn run_blocking<T, F>(work: F) -> Result<T, AppError>
+where
+    F: FnOnce() -> Result<T, AppError> + Send + 'static,
+    T: Send + 'static,
+{
+    let span = tracing::Span::current();
+    tauri::async_runtime::spawn_blocking(move || span.in_scope(work))
+        .await
+        .map_err(|e| AppError::IoError { reason: format!("Background task failed: {}", e) })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+    use std::time::Duration;
+
+    #[test]
+    fn concurrent_calls_run_in_parallel() {
+        // Each task waits until the other has started; if the calls were serialized
+        // the first would never get past the barrier and the timeout below would fire
+        let barrier = Arc::new(Barrier::new(2));
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+
+        std::thread::spawn(move || {
+            let results = tauri::async_runtime::block_on(async {
+                let tasks = (0..2).map(|i| {
+                    let barrier = Arc::clone(&barrier);
+                    run_blocking(move || {
+                        barrier.wait();
+                        Ok(i)
+                    })
+                });
+                let mut results = Vec::new();
+                for handle in tasks.map(tauri::async_runtime::spawn).collect::<Vec<_>>() {
+                    results.push(handle.await.unwrap().unwrap());
+                }
+                results
+            });
+            done_tx.send(results).ok();
+        });
+
+        let results = done_rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("concurrent run_blocking calls were serialized");
+        assert_eq!(results, vec![0, 1]);
+    }
+}
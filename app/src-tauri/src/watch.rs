@@ -0,0 +1,131 @@
+//! Watch the images directory for changes made outside the app (e.g. in Finder)
+//! so the storage browser stays in sync without polling
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::AppError;
+use crate::storage::{get_data_dir, parse_stored_image_name};
+
+/// Quiet period before queued changes are emitted (coalesces bulk deletes and copies)
+const IMAGES_WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// The running images watcher, if any (managed Tauri state)
+/// Dropping the watcher ends its debounce thread
+#[derive(Default)]
+pub struct ImagesWatch {
+    active: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl ImagesWatch {
+    fn stop(&self) {
+        self.active.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+    }
+}
+
+/// What happened to a stored image
+#[derive(Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// Payload of the `images-changed` event
+#[derive(Clone, serde::Serialize)]
+pub struct ImagesChanged {
+    pub kind: ImageChangeKind,
+    pub id: String,
+}
+
+/// Map a filesystem event on `path` to a stored image change
+/// Thumbnails and temp files (`.tmp`, `.partial`) are ignored; renames count as
+/// created or removed depending on which side of the rename `path` is
+fn image_change(kind: &EventKind, path: &Path) -> Option<(String, ImageChangeKind)> {
+    let filename = path.file_name()?.to_str()?;
+    let (id, is_thumbnail) = parse_stored_image_name(filename)?;
+    if is_thumbnail {
+        return None;
+    }
+    let change = match kind {
+        EventKind::Create(_) => ImageChangeKind::Created,
+        EventKind::Remove(_) => ImageChangeKind::Removed,
+        EventKind::Modify(ModifyKind::Name(_)) if path.exists() => ImageChangeKind::Created,
+        EventKind::Modify(ModifyKind::Name(_)) => ImageChangeKind::Removed,
+        EventKind::Modify(_) => ImageChangeKind::Modified,
+        _ => return None,
+    };
+    Some((id.to_string(), change))
+}
+
+/// Fold a new change into the queued one for the same id
+/// A file created then written within one window is still just "created"
+fn merge_change(queued: Option<ImageChangeKind>, change: ImageChangeKind) -> ImageChangeKind {
+    match (queued, change) {
+        (Some(ImageChangeKind::Created), ImageChangeKind::Modified) => ImageChangeKind::Created,
+        _ => change,
+    }
+}
+
+/// Start emitting `images-changed` events (`{ kind: "created" | "modified" | "removed", id }`)
+/// for files in the images directory; changes are debounced by 200ms and coalesced per id
+/// Starting a new watch stops the previous one
+#[tauri::command]
+pub fn images_watch_start(app: AppHandle, watch: State<'_, ImagesWatch>) -> Result<(), AppError> {
+    watch.stop();
+    let dir = get_data_dir()?;
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(|e| AppError::IoError { reason: format!("Failed to watch images directory: {}", e) })?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| AppError::IoError { reason: format!("Failed to watch images directory: {}", e) })?;
+
+    std::thread::spawn(move || {
+        let mut pending: BTreeMap<String, ImageChangeKind> = BTreeMap::new();
+        loop {
+            let received = if pending.is_empty() {
+                receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                receiver.recv_timeout(IMAGES_WATCH_DEBOUNCE)
+            };
+            match received {
+                Ok(Ok(event)) => {
+                    for path in &event.paths {
+                        if let Some((id, change)) = image_change(&event.kind, path) {
+                            let merged = merge_change(pending.get(&id).copied(), change);
+                            pending.insert(id, merged);
+                        }
+                    }
+                }
+                Ok(Err(e)) => tracing::warn!(error = %e, "images_watch_error"),
+                Err(timeout_or_disconnect) => {
+                    for (id, kind) in std::mem::take(&mut pending) {
+                        app.emit("images-changed", ImagesChanged { kind, id }).ok();
+                    }
+                    // The watcher (and with it the sender) was dropped by images_watch_stop
+                    if timeout_or_disconnect == RecvTimeoutError::Disconnected {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    *watch.active.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(watcher);
+    Ok(())
+}
+
+/// Stop the running images watch (no-op if none is running)
+#[tauri::command]
+pub fn images_watch_stop(watch: State<'_, ImagesWatch>) {
+    watch.stop();
+}
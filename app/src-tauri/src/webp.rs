@@ -0,0 +1,147 @@
+//! Lossy WebP encoding through libwebp
+//! The image crate only writes lossless WebP, which is usually larger than the JPEG
+//! it is meant to replace; libwebp honours the same 0-100 quality scale as JPEG
+
+use std::ffi::c_int;
+
+use image::DynamicImage;
+use libwebp_sys as sys;
+
+use crate::error::AppError;
+
+/// Chroma value of a neutral (gray) pixel
+const NEUTRAL_CHROMA: u8 = 128;
+
+fn encode_failed(what: &str) -> AppError {
+    AppError::EncodeFailed { reason: format!("Failed to encode WebP: {}", what) }
+}
+
+/// Owned WebPPicture, freed on drop
+struct Picture(sys::WebPPicture);
+
+impl Drop for Picture {
+    fn drop(&mut self) {
+        // SAFETY: the picture was initialized by WebPPicture::new; freeing an
+        // unallocated picture is a no-op
+        unsafe { sys::WebPPictureFree(&mut self.0) };
+    }
+}
+
+/// Full-range gray level to video-range luma (16-235), as libwebp's own RGB import
+/// produces for gray pixels, so decoders restore the original tone
+fn video_range_luma(level: u8) -> u8 {
+    (16 + (u32::from(level) * 219 + 127) / 255) as u8
+}
+
+/// Fill `picture` (YUV420) from a grayscale image: luma plane from the pixels, chroma
+/// planes neutral, so no RGB copy is made
+fn fill_luma(picture: &mut sys::WebPPicture, gray: &image::GrayImage) -> Result<(), AppError> {
+    picture.use_argb = 0;
+    picture.colorspace = sys::WebPEncCSP::WEBP_YUV420;
+    // SAFETY: width/height are set, so WebPPictureAlloc sizes the Y/U/V planes for them
+    if unsafe { sys::WebPPictureAlloc(picture) } == 0 {
+        return Err(encode_failed("out of memory"));
+    }
+    let (width, height) = (gray.width() as usize, gray.height() as usize);
+    let (uv_width, uv_height) = (width.div_ceil(2), height.div_ceil(2));
+    // SAFETY: each plane row lies within the allocation: y rows are `y_stride` apart and
+    // at least `width` long, u/v rows `uv_stride` apart and at least `uv_width` long
+    unsafe {
+        for (y, row) in gray.rows().enumerate() {
+            let plane = std::slice::from_raw_parts_mut(picture.y.add(y * picture.y_stride as usize), width);
+            for (dst, src) in plane.iter_mut().zip(row) {
+                *dst = video_range_luma(src.0[0]);
+            }
+        }
+        for y in 0..uv_height {
+            let offset = y * picture.uv_stride as usize;
+            std::slice::from_raw_parts_mut(picture.u.add(offset), uv_width).fill(NEUTRAL_CHROMA);
+            std::slice::from_raw_parts_mut(picture.v.add(offset), uv_width).fill(NEUTRAL_CHROMA);
+        }
+    }
+    Ok(())
+}
+
+/// Encode `img` as lossy WebP at `quality` (0-100)
+/// Grayscale images are encoded from their luma directly; alpha is dropped like JPEG
+pub fn encode_lossy(img: &DynamicImage, quality: u8) -> Result<Vec<u8>, AppError> {
+    let mut config = sys::WebPConfig::new().map_err(|()| encode_failed("libwebp version mismatch"))?;
+    config.quality = f32::from(quality.min(100));
+    let mut picture = Picture(sys::WebPPicture::new().map_err(|()| encode_failed("libwebp version mismatch"))?);
+    picture.0.width = img.width() as c_int;
+    picture.0.height = img.height() as c_int;
+
+    if img.color().has_color() {
+        let rgb = img.to_rgb8();
+        picture.0.use_argb = 1;
+        // SAFETY: `rgb` holds width * height packed RGB pixels, `width * 3` bytes per row
+        let imported = unsafe { sys::WebPPictureImportRGB(&mut picture.0, rgb.as_ptr(), (rgb.width() * 3) as c_int) };
+        if imported == 0 {
+            return Err(encode_failed("out of memory"));
+        }
+    } else {
+        fill_luma(&mut picture.0, &img.to_luma8())?;
+    }
+
+    // SAFETY: the writer outlives WebPEncode, which only appends to it through
+    // WebPMemoryWrite; its buffer is copied out and then released
+    unsafe {
+        let mut writer: sys::WebPMemoryWriter = std::mem::zeroed();
+        sys::WebPMemoryWriterInit(&mut writer);
+        picture.0.writer = Some(sys::WebPMemoryWrite);
+        picture.0.custom_ptr = (&mut writer as *mut sys::WebPMemoryWriter).cast();
+        let encoded = sys::WebPEncode(&config, &mut picture.0);
+        let bytes = if writer.mem.is_null() {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(writer.mem, writer.size).to_vec()
+        };
+        sys::WebPMemoryWriterClear(&mut writer);
+        if encoded == 0 {
+            return Err(encode_failed(&format!("{:?}", picture.0.error_code)));
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Receipt-like grayscale: ink rows on paper with deterministic noise
+    fn receipt_gray() -> image::GrayImage {
+        image::GrayImage::from_fn(320, 240, |x, y| {
+            let noise = ((x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 7) % 9;
+            let ink = y % 24 < 10 && x % 12 < 7;
+            image::Luma([if ink { 20 + noise as u8 } else { 200 + noise as u8 }])
+        })
+    }
+
+    #[test]
+    fn quality_controls_size_and_gray_tone_survives() {
+        let img = DynamicImage::ImageLuma8(receipt_gray());
+        let low = encode_lossy(&img, 30).unwrap();
+        let high = encode_lossy(&img, 90).unwrap();
+        assert!(low.len() < high.len(), "q30 {} >= q90 {}", low.len(), high.len());
+
+        let decoded = image::load_from_memory(&high).unwrap().to_luma8();
+        assert_eq!(decoded.dimensions(), (320, 240));
+        let mean_diff = decoded
+            .pixels()
+            .zip(receipt_gray().pixels())
+            .map(|(a, b)| f64::from(a.0[0].abs_diff(b.0[0])))
+            .sum::<f64>()
+            / (320.0 * 240.0);
+        assert!(mean_diff < 8.0, "mean difference {}", mean_diff);
+    }
+
+    #[test]
+    fn color_images_encode() {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_fn(65, 33, |x, y| {
+            image::Rgb([(x * 3) as u8, (y * 7) as u8, 128])
+        }));
+        let decoded = image::load_from_memory(&encode_lossy(&img, 75).unwrap()).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (65, 33));
+        assert!(decoded.color().has_color());
+    }
+}
@@ -32,12 +32,15 @@ export async function getDeviceId(): Promise<UserIdType> {
   }
 
   try {
-    // Get machine ID from Rust backend
-    const machineId = await invoke<string>('get_machine_id');
-    const deviceId = `device-${machineId}`;
+    // Get machine ID from Rust backend (falls back to a persisted UUID if the OS ID is unreadable)
+    const machineId = await invoke<{ id: string; is_fallback: boolean }>('get_machine_id');
+    const deviceId = `device-${machineId.id}`;
 
     cachedDeviceId = UserId(deviceId);
-    logger.info(EVENTS.DEVICE_ID_LOADED, { deviceId: deviceId.slice(0, 20) + '...' });
+    logger.info(EVENTS.DEVICE_ID_LOADED, {
+      deviceId: deviceId.slice(0, 20) + '...',
+      isFallback: machineId.is_fallback,
+    });
 
     return cachedDeviceId;
   } catch (error) {
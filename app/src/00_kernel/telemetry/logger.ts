@@ -288,7 +288,7 @@ export function logStateTransition(params: {
 
 /**
  * Initialize logging system.
- * - Cleans up old log files (> 7 days)
+ * - Cleans up old log files (older than the saved retention, default 7 days)
  * Call once at app startup.
  */
 export async function initLogger(): Promise<void> {
@@ -298,9 +298,15 @@ export async function initLogger(): Promise<void> {
   }
 
   try {
-    const deleted = await invoke<number>('log_cleanup', { retentionDays: 7 });
-    if (deleted > 0) {
-      logger.info(EVENTS.APP_STARTED, { logFilesCleanedUp: deleted });
+    // No retentionDays: Rust applies the retention saved in Settings
+    const result = await invoke<{
+      deleted_count: number;
+      compressed_count: number;
+      freed_bytes: number;
+      oldest_kept_date: string | null;
+    }>('log_cleanup', {});
+    if (result.deleted_count > 0) {
+      logger.info(EVENTS.APP_STARTED, { logFilesCleanedUp: result.deleted_count });
     }
   } catch {
     // Silently ignore cleanup errors
@@ -17,6 +17,55 @@ interface CompressResult {
   width: number;
   height: number;
   md5: string;
+  quality_used: number | null;
+  max_dimension_used: number;
+  grayscale: boolean;
+  chosen_format: string;
+  crop_applied: { x: number; y: number; width: number; height: number } | null;
+  was_passthrough: boolean;
+  timings_ms: {
+    decode: number;
+    resize: number;
+    grayscale: number;
+    encode: number;
+    hash: number;
+    total: number;
+  };
+  gray_levels_used: number | null;
+  original_stored_path: string | null;
+  enhanced: boolean;
+}
+
+// Structured error shape matching Rust AppError
+interface AppErrorPayload {
+  code: string;
+  message: string;
+}
+
+/**
+ * Error thrown when a Rust command rejects
+ * `code` is stable (e.g. FILE_NOT_FOUND, DECODE_FAILED) and safe to branch on
+ */
+export class IpcError extends Error {
+  readonly code: string;
+
+  constructor(code: string, message: string) {
+    super(message);
+    this.name = 'IpcError';
+    this.code = code;
+  }
+}
+
+/**
+ * Convert a rejected invoke() value into an Error
+ * Pillar B: Validate at boundary
+ */
+function toIpcError(raw: unknown): Error {
+  const payload = raw as AppErrorPayload | null;
+  if (payload && typeof payload.code === 'string' && typeof payload.message === 'string') {
+    return new IpcError(payload.code, payload.message);
+  }
+  return raw instanceof Error ? raw : new Error(String(raw));
 }
 
 // Validated result type for domain use
@@ -86,6 +135,8 @@ export async function compressImage(
     invoke<CompressResult>('compress_image', {
       inputPath,
       imageId: String(imageId),
+    }).catch((e: unknown) => {
+      throw toIpcError(e);
     }),
     COMPRESS_TIMEOUT_MS,
     'Compression timeout (15s)'
@@ -112,7 +163,9 @@ export async function compressImage(
  * Used for duplicate detection before compression
  */
 export async function getImageHash(path: string): Promise<string> {
-  const hash = await invoke<string>('get_image_hash', { path });
+  const hash = await invoke<string>('get_image_hash', { path }).catch((e: unknown) => {
+    throw toIpcError(e);
+  });
 
   if (typeof hash !== 'string' || hash.length !== 32) {
     throw new Error('Invalid hash response');
@@ -125,5 +178,7 @@ export async function getImageHash(path: string): Promise<string> {
  * Delete a local image file
  */
 export async function deleteLocalImage(path: string): Promise<void> {
-  await invoke('delete_file', { path });
+  await invoke('delete_file', { path }).catch((e: unknown) => {
+    throw toIpcError(e);
+  });
 }